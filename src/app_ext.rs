@@ -6,6 +6,7 @@ use bevy::ecs::component::Tick;
 use bevy::ecs::entity::MapEntities;
 use bevy::prelude::*;
 use bevy_replicon::{core::replication::{replication_registry::rule_fns::RuleFns, replication_rules::GroupReplication}, prelude::AppRuleExt};
+use bevy_replicon::prelude::ClientId;
 use serde::{de::DeserializeOwned, Serialize};
 
 //standard shortcuts
@@ -15,27 +16,26 @@ use serde::{de::DeserializeOwned, Serialize};
 
 /// Default component repair for [`AppReplicationRepairExt`].
 ///
-/// The component `C` will be removed from `entity` if the component was not added/changed on the entity in the repair
-/// tick.
+/// The component `C` will be removed from `entity` if the component was not replicated to the entity in the first
+/// replication message after a reconnect. See [`component_was_replicated`].
 ///
-/// If you manually added/changed the component on the entity in the repair tick, it may be erroneously left in place.
-/// Likewise, if you are not replicating the component and instead manually inserted it, it may be erroneously removed.
+/// If you are not replicating the component and instead manually inserted it, it may be erroneously removed.
 ///
-/// You can disable this function for a client entity by adding a [`Retain<C>`](crate::Retain) component to it.
+/// You can disable this function for a client entity by adding a [`Retain<C>`](crate::Retain) component to it,
+/// or a [`RepairOverride`](crate::RepairOverride) with `retain_all` set.
+///
+/// Triggers [`RepairRemoved<C>`](crate::RepairRemoved) on the entity with the removed component value.
 pub fn repair_component<C: Component>(entity: &mut EntityWorldMut, preinit_tick: Tick)
 {
-    let world_tick = unsafe { entity.world_mut().change_tick() };
-
     // check if the component should be retained
-    if entity.contains::<Retain<C>>() { return; };
+    if is_retained::<C>(entity) { return; }
 
-    // check if the component exists on the entity
-    let Some(change_ticks) = entity.get_change_ticks::<C>() else { return; };
+    // check if the component was written by the most recent replication message
+    if component_was_replicated::<C>(entity, preinit_tick) { return; }
 
-    // check if the component was mutated by the most recent replication message
-    if change_ticks.is_changed(preinit_tick, world_tick) { return; }
-
-    entity.remove::<C>();
+    let Some(value) = entity.take::<C>() else { return; };
+    let entity_id = entity.id();
+    entity.trigger(RepairRemoved{ entity: entity_id, value });
 }
 
 //-------------------------------------------------------------------------------------------------------------------
@@ -48,6 +48,22 @@ pub trait AppReplicationRepairExt
     where
         C: Component + Serialize + DeserializeOwned;
 
+    /// Mirrors [`AppRuleExt::replicate`](bevy_replicon::prelude::AppRuleExt::replicate) using the default
+    /// component-removal repair function [`repair_component`].
+    ///
+    /// When the first replication message after a reconnect is received, the incoming value is compared with the
+    /// existing value and only written if they differ. This way `Changed` filters are not triggered for components
+    /// whose values did not change while disconnected.
+    fn replicate_repair_eq<C>(&mut self) -> &mut Self
+    where
+        C: Component + PartialEq + Serialize + DeserializeOwned;
+
+    /// Mirrors [`AppRuleExt::replicate`](bevy_replicon::prelude::AppRuleExt::replicate) using a declarative
+    /// [`RepairPolicy`].
+    fn replicate_repair_policy<C>(&mut self, policy: RepairPolicy<C>) -> &mut Self
+    where
+        C: Component + Serialize + DeserializeOwned;
+
     /// Mirrors [`AppRuleExt::replicate_mapped`](bevy_replicon::prelude::AppRuleExt::replicate_mapped) using
     /// the default component-removal repair function [`repair_component`].
     fn replicate_repair_mapped<C>(&mut self) -> &mut Self
@@ -73,13 +89,97 @@ pub trait AppReplicationRepairExt
     where
         C: GroupReplication;
 
+    /// Mirrors [`AppRuleExt::replicate_group`](bevy_replicon::prelude::AppRuleExt::replicate_group) using
+    /// [`repair_group_component`] for each component in the group.
+    ///
+    /// Components in the group can be retained individually with [`Retain<C>`](crate::Retain), or all together with
+    /// [`Retain<G>`](crate::Retain).
+    ///
+    /// If a component is in multiple groups registered this way, only the last registered group's rule is kept.
+    fn replicate_repair_group_components<G>(&mut self) -> &mut Self
+    where
+        G: GroupRepair;
+
     /// Registers a user-defined component-removal repair function.
     ///
     /// This can be used for components that were already registered for replication via `bevy_replicon`'s API.
+    ///
+    /// Registering the same function multiple times has no effect.
+    ///
+    /// The function will be called on every replicated entity during repair. Prefer
+    /// [`add_replication_repair_policy`](Self::add_replication_repair_policy), which is only called on entities with
+    /// the repaired component.
     fn add_replication_repair_fn(
         &mut self,
         repair: RepairComponentFn,
     ) -> &mut Self;
+
+    /// Registers a user-defined component-removal repair system.
+    ///
+    /// The system receives each repaired client entity and the world change tick from before the first server init
+    /// message for the current session (see [`RepairComponentFn`]). It can use any system parameters, e.g. to read
+    /// resources that decide which components to keep, and can store state in [`Local`] parameters.
+    ///
+    /// Like [`add_replication_repair_fn`](Self::add_replication_repair_fn), the system will be called on every
+    /// replicated entity during repair. Deferred system parameters such as [`Commands`] are applied after each
+    /// call.
+    fn add_replication_repair_system<M>(
+        &mut self,
+        system: impl IntoSystem<In<(Entity, Tick)>, (), M>,
+    ) -> &mut Self;
+
+    /// Registers a [`RepairPolicy`] for `C`.
+    ///
+    /// This can be used for components that were already registered for replication via `bevy_replicon`'s API.
+    ///
+    /// Replaces the existing rule for `C` with a warning if there is one.
+    fn add_replication_repair_policy<C>(
+        &mut self,
+        policy: RepairPolicy<C>,
+    ) -> &mut Self
+    where
+        C: Component;
+
+    /// Returns the registered component repair rules.
+    fn repair_rules(&mut self) -> &ComponentRepairRules;
+
+    /// Registers the channels used to detect server restarts and replication protocol mismatches.
+    ///
+    /// When this is registered, [`ServerPlugin`] sends its [`ServerSessionEpoch`] to each client when it connects.
+    /// [`ClientPlugin`] waits for the epoch before applying replication messages, and if the epoch differs from the
+    /// previous connection's epoch then all client state from the previous session is discarded instead of repaired
+    /// (see [`SessionReset`]).
+    ///
+    /// The server and client also exchange their [`replication_fingerprint`]s. If they differ, then
+    /// [`ProtocolMismatch`] is sent on the client and [`ClientProtocolMismatch`] is sent on the server.
+    ///
+    /// This must be called in both the server and client apps, at the same point relative to other `bevy_replicon`
+    /// channel registrations (e.g. server events), so that channel ids match.
+    fn add_repair_session_channel(&mut self) -> &mut Self;
+
+    /// Sets the system used by [`ServerPlugin`] to resolve the persistent [`ClientIdentity`] of connecting clients.
+    ///
+    /// [`ServerPlugin`] normally returns cached client entity mappings to a reconnecting client only if it reconnects
+    /// with the same `ClientId`. If your transport assigns a new `ClientId` on each connection, use this to identify
+    /// clients by e.g. an account id or session token sent with authentication. When a client connects, the system
+    /// is called with its `ClientId`, and cached mappings of previous clients with the same identity are moved to the
+    /// new `ClientId`.
+    ///
+    /// The system runs in [`PostUpdate`] when `bevy_replicon`'s `ServerEvent::ClientConnected` is received, so
+    /// identity data must be available by then. Return `None` if the client's identity is unknown.
+    ///
+    /// Replaces the previous resolver if there is one.
+    fn set_client_identity_resolver<M>(
+        &mut self,
+        system: impl IntoSystem<In<ClientId>, Option<ClientIdentity>, M>,
+    ) -> &mut Self;
+
+    /// Registers a component that makes replicated entities [`Ephemeral`].
+    ///
+    /// Replicated client entities with `C` will be despawned as soon as the client disconnects.
+    fn add_ephemeral_component<C>(&mut self) -> &mut Self
+    where
+        C: Component;
 }
 
 impl AppReplicationRepairExt for App {
@@ -93,6 +193,28 @@ impl AppReplicationRepairExt for App {
             )
     }
 
+    fn replicate_repair_eq<C>(&mut self) -> &mut Self
+    where
+        C: Component + PartialEq + Serialize + DeserializeOwned,
+    {
+        self.replicate::<C>();
+        track_replication_eq::<C>(self);
+        self.add_replication_repair_policy(RepairPolicy::<C>::Remove);
+
+        self
+    }
+
+    fn replicate_repair_policy<C>(&mut self, policy: RepairPolicy<C>) -> &mut Self
+    where
+        C: Component + Serialize + DeserializeOwned,
+    {
+        self.replicate::<C>();
+        track_replication::<C>(self);
+        self.add_replication_repair_policy(policy);
+
+        self
+    }
+
     fn replicate_repair_mapped<C>(&mut self) -> &mut Self
     where
         C: Component + Serialize + DeserializeOwned + MapEntities,
@@ -112,7 +234,8 @@ impl AppReplicationRepairExt for App {
         C: Component,
     {
         self.replicate_with::<C>(rules);
-        self.add_replication_repair_fn(repair);
+        track_replication::<C>(self);
+        self.add_replication_repair_policy(RepairPolicy::<C>::Custom(repair));
 
         self
     }
@@ -130,6 +253,16 @@ impl AppReplicationRepairExt for App {
         self
     }
 
+    fn replicate_repair_group_components<G>(&mut self) -> &mut Self
+    where
+        G: GroupRepair
+    {
+        self.replicate_group::<G>();
+        G::register_repair(self);
+
+        self
+    }
+
     fn add_replication_repair_fn(
         &mut self,
         repair: RepairComponentFn,
@@ -138,7 +271,81 @@ impl AppReplicationRepairExt for App {
         if !self.world().contains_resource::<ComponentRepairRules>()
         { self.world_mut().init_resource::<ComponentRepairRules>(); }
 
-        self.world_mut().resource_mut::<ComponentRepairRules>().push(repair);
+        self.world_mut().resource_mut::<ComponentRepairRules>().add_untyped(repair);
+
+        self
+    }
+
+    fn add_replication_repair_system<M>(
+        &mut self,
+        system: impl IntoSystem<In<(Entity, Tick)>, (), M>,
+    ) -> &mut Self
+    {
+        if !self.world().contains_resource::<ComponentRepairRules>()
+        { self.world_mut().init_resource::<ComponentRepairRules>(); }
+
+        let mut system = IntoSystem::into_system(system);
+        system.initialize(self.world_mut());
+        let name = system.name();
+        let repair = move |entity: &mut EntityWorldMut, preinit_tick: Tick|
+        {
+            let entity_id = entity.id();
+            entity.world_scope(|world| system.run((entity_id, preinit_tick), world));
+        };
+        self.world_mut().resource_mut::<ComponentRepairRules>().add_system(name, Box::new(repair));
+
+        self
+    }
+
+    fn add_replication_repair_policy<C>(
+        &mut self,
+        policy: RepairPolicy<C>,
+    ) -> &mut Self
+    where
+        C: Component,
+    {
+        if !self.world().contains_resource::<ComponentRepairRules>()
+        { self.world_mut().init_resource::<ComponentRepairRules>(); }
+
+        let info = RepairRuleInfo::new::<C>(self.world_mut());
+        self.world_mut().resource_mut::<ComponentRepairRules>().add_typed(info, policy.into_repair_fn());
+
+        self
+    }
+
+    fn repair_rules(&mut self) -> &ComponentRepairRules
+    {
+        if !self.world().contains_resource::<ComponentRepairRules>()
+        { self.world_mut().init_resource::<ComponentRepairRules>(); }
+
+        self.world().resource::<ComponentRepairRules>()
+    }
+
+    fn add_repair_session_channel(&mut self) -> &mut Self
+    {
+        register_session_channel(self);
+        self
+    }
+
+    fn set_client_identity_resolver<M>(
+        &mut self,
+        system: impl IntoSystem<In<ClientId>, Option<ClientIdentity>, M>,
+    ) -> &mut Self
+    {
+        set_client_identity_resolver(self, system);
+        self
+    }
+
+    fn add_ephemeral_component<C>(&mut self) -> &mut Self
+    where
+        C: Component,
+    {
+        if !self.world().contains_resource::<EphemeralComponents>()
+        { self.world_mut().init_resource::<EphemeralComponents>(); }
+
+        let component_id = self.world_mut().register_component::<C>();
+        let mut ephemeral = self.world_mut().resource_mut::<EphemeralComponents>();
+        if !ephemeral.contains(&component_id) { ephemeral.push(component_id); }
 
         self
     }
@@ -0,0 +1,123 @@
+//local shortcuts
+
+//third-party shortcuts
+use bevy::prelude::*;
+
+//standard shortcuts
+use std::time::Duration;
+
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Controls what [`ClientPlugin`](crate::ClientPlugin) does with client state when the client disconnects.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum DisconnectStrategy
+{
+    /// Preserve replicated entities while disconnected and repair them after reconnecting.
+    #[default]
+    Repair,
+    /// Despawn all replicated entities and reset `bevy_replicon`'s client state as soon as the client disconnects.
+    ///
+    /// The repair lifecycle still runs, so lifecycle events are sent and [`Prespawned`](crate::Prespawned) entities
+    /// are cleaned up after reconnecting if [`ClientRepairConfig::cleanup_prespawns`] is set.
+    Reset,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Controls whether [`ClientPlugin`](crate::ClientPlugin) repairs client state after a reconnect, based on how long
+/// the client was disconnected.
+///
+/// If the policy rejects the disconnect duration, all client state from the previous session is discarded when the
+/// client reconnects, before any replication messages are applied, and [`SessionReset`](crate::SessionReset) is sent.
+/// The new session is then replicated from scratch.
+#[derive(Debug, Default, Copy, Clone)]
+pub enum ReconnectPolicy
+{
+    /// Always repair.
+    #[default]
+    AlwaysRepair,
+    /// Repair if the client was disconnected for at most this long.
+    MaxDuration(Duration),
+    /// Repair if the function returns `true` for the disconnect duration.
+    Custom(fn(Duration) -> bool),
+}
+
+impl ReconnectPolicy
+{
+    /// Returns `true` if client state should be repaired after being disconnected for `duration`.
+    pub fn should_repair(&self, duration: Duration) -> bool
+    {
+        match self
+        {
+            Self::AlwaysRepair     => true,
+            Self::MaxDuration(max) => duration <= *max,
+            Self::Custom(f)        => (f)(duration),
+        }
+    }
+}
+
+impl PartialEq for ReconnectPolicy
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        match (self, other)
+        {
+            (Self::AlwaysRepair, Self::AlwaysRepair)     => true,
+            (Self::MaxDuration(a), Self::MaxDuration(b)) => a == b,
+            (Self::Custom(a), Self::Custom(b))           => *a as usize == *b as usize,
+            _                                            => false,
+        }
+    }
+}
+
+impl Eq for ReconnectPolicy {}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Runtime configuration for [`ClientPlugin`](crate::ClientPlugin).
+///
+/// The config is read when the client disconnects and applies until the next disconnect, so changes take effect
+/// in the next connection session. Changes made while disconnected or repairing do not affect the current repair.
+///
+/// If this resource is not inserted before [`ClientPlugin`](crate::ClientPlugin) is added, it will be initialized
+/// with [`ClientPlugin::cleanup_prespawns`](crate::ClientPlugin::cleanup_prespawns).
+#[derive(Resource, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ClientRepairConfig
+{
+    /// If `false`, the client plugin does nothing when the client disconnects and `bevy_replicon`'s default
+    /// client reset runs instead. [`ClientRepairState`](crate::ClientRepairState) will be set to
+    /// [`ClientRepairState::Dormant`](crate::ClientRepairState::Dormant).
+    ///
+    /// Defaults to `true`.
+    pub enabled: bool,
+    /// See [`ClientPlugin::cleanup_prespawns`](crate::ClientPlugin::cleanup_prespawns).
+    ///
+    /// Defaults to `false`.
+    pub cleanup_prespawns: bool,
+    /// What to do with client state when the client disconnects.
+    ///
+    /// Defaults to [`DisconnectStrategy::Repair`].
+    pub strategy: DisconnectStrategy,
+    /// Whether to repair or reset client state after reconnecting, based on how long the client was disconnected.
+    ///
+    /// Only used with [`DisconnectStrategy::Repair`].
+    ///
+    /// Defaults to [`ReconnectPolicy::AlwaysRepair`].
+    pub reconnect_policy: ReconnectPolicy,
+}
+
+impl Default for ClientRepairConfig
+{
+    fn default() -> Self
+    {
+        Self{
+            enabled: true,
+            cleanup_prespawns: false,
+            strategy: DisconnectStrategy::default(),
+            reconnect_policy: ReconnectPolicy::default(),
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
@@ -0,0 +1,171 @@
+//local shortcuts
+
+//third-party shortcuts
+use bevy::prelude::*;
+use bevy_replicon::core::replicon_tick::RepliconTick;
+
+//standard shortcuts
+use std::time::Duration;
+
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Summary of the work done by a client repair pass.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct RepairCounts
+{
+    /// Number of replicated entities despawned because they failed to re-replicate.
+    pub despawned_entities: usize,
+    /// Number of [`Prespawned`](crate::Prespawned) entities despawned because they failed to replicate.
+    pub despawned_prespawns: usize,
+    /// Number of replicated entities visited by component repair.
+    pub repaired_entities: usize,
+    /// Number of components removed from replicated entities by component repair.
+    pub removed_components: usize,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ClientPlugin`](crate::ClientPlugin) when the client disconnects.
+///
+/// State transition: `* -> ClientRepairState::Disconnected`.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Disconnected
+{
+    /// The last server tick received before disconnecting.
+    pub tick: RepliconTick,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ClientPlugin`](crate::ClientPlugin) when the client starts reconnecting.
+///
+/// State transition: `ClientRepairState::Disconnected -> ClientRepairState::Waiting`.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ReconnectWaiting
+{
+    /// The last server tick received before disconnecting.
+    pub tick: RepliconTick,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ClientPlugin`](crate::ClientPlugin) when the first replication message after a reconnect
+/// arrives.
+///
+/// State transition: `ClientRepairState::Waiting -> ClientRepairState::Repairing`.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RepairStarted
+{
+    /// The server tick of the first replication message after reconnecting.
+    pub tick: RepliconTick,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ClientPlugin`](crate::ClientPlugin) when repair is done.
+///
+/// State transition: `ClientRepairState::Repairing -> ClientRepairState::Done`.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RepairFinished
+{
+    /// The server tick of the replication message that was repaired against.
+    pub tick: RepliconTick,
+    /// What the repair did.
+    pub counts: RepairCounts,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Entity-targeted observer trigger for replicated or [`Prespawned`](crate::Prespawned) entities despawned by
+/// client repair.
+///
+/// This is triggered immediately before the entity is despawned (or marked with
+/// [`RepairDespawning`](crate::RepairDespawning) if despawns are deferred), so the entity is still accessible to
+/// observers.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RepairDespawned
+{
+    /// The client entity being despawned.
+    pub entity: Entity,
+    /// The server entity that was mapped to the client entity, if any.
+    ///
+    /// For replicated entities, the mapping will already be removed from `ServerEntityMap` when this is triggered.
+    pub server_entity: Option<Entity>,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Entity-targeted observer trigger for components removed by [`repair_component`](crate::repair_component), or
+/// replaced by [`reset_component`](crate::reset_component) and [`RepairPolicy::ReplaceWith`](crate::RepairPolicy).
+///
+/// The component is taken from the entity and handed to observers, so it will not be on the entity when this is
+/// triggered. Replaced components will already hold their replacement values.
+#[derive(Event, Debug)]
+pub struct RepairRemoved<C: Component>
+{
+    /// The client entity the component was removed from.
+    pub entity: Entity,
+    /// The removed component value.
+    pub value: C,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Why a [`SessionReset`] happened.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SessionResetReason
+{
+    /// The server's [`ServerSessionEpoch`](crate::ServerSessionEpoch) changed since the previous connection, e.g.
+    /// because the server process restarted.
+    ServerRestarted
+    {
+        /// The epoch of the previous server session.
+        previous_epoch: u64,
+        /// The epoch of the new server session.
+        epoch: u64,
+    },
+    /// The client was disconnected for longer than allowed by
+    /// [`ClientRepairConfig::reconnect_policy`](crate::ClientRepairConfig::reconnect_policy).
+    DisconnectedTooLong
+    {
+        /// How long the client was disconnected.
+        duration: Duration,
+    },
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ClientPlugin`](crate::ClientPlugin) when client state from the previous server session is
+/// discarded instead of repaired.
+///
+/// All replicated entities are despawned and `bevy_replicon`'s client state is reset before the first replication
+/// message of the new session is applied. The repair lifecycle continues as normal afterward.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SessionReset
+{
+    /// Why the session was reset.
+    pub reason: SessionResetReason,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ClientPlugin`](crate::ClientPlugin) when the server's
+/// [`replication_fingerprint`](crate::replication_fingerprint) does not match the client's.
+///
+/// The client and server registered different replicated components (or registered them in a different order), so
+/// replication messages from the server can't be applied safely. When this is sent, all client state from the previous
+/// server session has been discarded, and replication messages are not applied for the rest of the connection. You
+/// should disconnect the client.
+///
+/// The server sends [`ClientProtocolMismatch`](crate::ClientProtocolMismatch) for the same connection.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ProtocolMismatch
+{
+    /// The server's fingerprint.
+    pub server_fingerprint: u64,
+    /// The client's fingerprint.
+    pub client_fingerprint: u64,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
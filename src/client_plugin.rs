@@ -2,17 +2,20 @@
 use crate::*;
 
 //third-party shortcuts
-use bevy::ecs::component::Tick;
+use bevy::ecs::component::{ComponentId, Tick};
 use bevy::ecs::entity::EntityHashSet;
 use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::utils::Instant;
 use bevy_cobweb::prelude::*;
 use bevy_replicon::client::confirm_history::ConfirmHistory;
 use bevy_replicon::client::{BufferedMutations, ServerUpdateTick};
+use bevy_replicon::core::replicon_tick::RepliconTick;
 use bevy_replicon::core::server_entity_map::ServerEntityMap;
 use bevy_replicon::prelude::*;
 
 //standard shortcuts
-
+use std::time::Duration;
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
@@ -33,6 +36,270 @@ impl Default for RepairChangeTickTracker { fn default() -> Self { Self(Tick::new
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
+#[derive(Resource, Default, Deref)]
+struct RepairDespawnModeConfig(RepairDespawnMode);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// The [`ClientRepairConfig`] for the current session, captured when the client disconnects.
+#[derive(Resource, Deref)]
+struct ActiveRepairConfig(ClientRepairConfig);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Replicated entities waiting for component repair.
+#[derive(Resource, Default)]
+struct ComponentRepairQueue
+{
+    /// Max time to spend repairing components each tick.
+    budget: Option<Duration>,
+    pending: Vec<Entity>,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Tracks server session epochs received on the [`RepairSessionChannel`].
+#[derive(Resource, Default)]
+struct ServerSessionTracker
+{
+    /// The most recent epoch received.
+    epoch: Option<u64>,
+    /// Whether the epoch was received in the current connection.
+    received: bool,
+    /// Whether the server's replication fingerprint did not match in the current connection.
+    mismatched: bool,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// When the client disconnected, if client state should be checked against
+/// [`ClientRepairConfig::reconnect_policy`] on the next connection.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct DisconnectedAt(Option<Instant>);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Accumulates [`RepairCounts`] for the current repair pass.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct RepairCountsTracker(RepairCounts);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Captures [`ClientRepairConfig`] for the next session when the client disconnects.
+///
+/// If repair is disabled, pending [`RepairDespawning`] entities are despawned since they won't be repaired.
+fn apply_repair_config(
+    mut commands : Commands,
+    config       : Res<ClientRepairConfig>,
+    mut active   : ResMut<ActiveRepairConfig>,
+    mut state    : ResMut<ClientRepairState>,
+    despawning   : Query<Entity, With<RepairDespawning>>,
+){
+    active.0 = *config;
+    if active.enabled { return; }
+
+    if state.not_in_state(ClientRepairState::Dormant)
+    {
+        state.set(ClientRepairState::Dormant);
+    }
+    for entity in despawning.iter()
+    {
+        let Some(entity_commands) = commands.get_entity(entity) else { continue; };
+        entity_commands.despawn_recursive();
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn repair_enabled(active: Res<ActiveRepairConfig>) -> bool
+{
+    active.enabled
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn cleanup_prespawns_enabled(active: Res<ActiveRepairConfig>) -> bool
+{
+    active.cleanup_prespawns
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn reset_strategy(active: Res<ActiveRepairConfig>) -> bool
+{
+    active.strategy == DisconnectStrategy::Reset
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Despawns all replicated entities and resets `bevy_replicon`'s client state, mirroring `ClientSet::Reset`.
+fn clear_server_session(world: &mut World)
+{
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<Replicated>, With<RepairDespawning>)>>()
+        .iter(world)
+        .collect();
+    for entity in entities
+    {
+        let Ok(entity) = world.get_entity_mut(entity) else { continue; };
+        entity.despawn_recursive();
+    }
+
+    world.resource_mut::<ServerEntityMap>().clear();
+    world.resource_mut::<BufferedMutations>().clear();
+
+    // don't mark the tick as changed, otherwise it looks like a replication message arrived
+    if **world.resource::<ServerUpdateTick>() != RepliconTick::default()
+    {
+        *world.resource_mut::<ServerUpdateTick>().bypass_change_detection() = Default::default();
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn forget_session_epoch(mut tracker: ResMut<ServerSessionTracker>)
+{
+    tracker.received = false;
+    tracker.mismatched = false;
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Records when the client disconnected.
+///
+/// Failed connection attempts also count as disconnects, so the time of the first disconnect is kept until the client
+/// connects again.
+fn record_disconnect_time(mut disconnected_at: ResMut<DisconnectedAt>)
+{
+    disconnected_at.get_or_insert_with(Instant::now);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Discards the previous session instead of repairing it if the client was disconnected for too long.
+///
+/// Runs in the tick the client connects, so the old session is gone before the new session's first message.
+fn check_disconnect_duration(world: &mut World)
+{
+    let Some(disconnected_at) = world.resource_mut::<DisconnectedAt>().take() else { return; };
+    let duration = disconnected_at.elapsed();
+    if world.resource::<ActiveRepairConfig>().reconnect_policy.should_repair(duration) { return; }
+
+    clear_server_session(world);
+    world.send_event(SessionReset{ reason: SessionResetReason::DisconnectedTooLong{ duration } });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Sends the client's replication fingerprint to the server.
+fn send_client_session_info(
+    mut client  : ResMut<RepliconClient>,
+    channel     : Res<RepairSessionChannel>,
+    fingerprint : Res<ReplicationFingerprint>,
+){
+    let message = bincode::serialize(&ClientSessionInfo{ fingerprint: fingerprint.0 })
+        .expect("client session info should serialize");
+    client.send(channel.client, message);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Reads the server's session epoch and discards the previous session if the server restarted.
+///
+/// If the server's replication fingerprint does not match, then the previous session is discarded and replication
+/// messages are discarded for the rest of the connection (see [`discard_blocked_messages`]).
+///
+/// `ClientSet::Receive` waits until this receives the epoch, so a restarted server's entities are never mapped to
+/// entities from the previous server process.
+fn receive_session_info(world: &mut World)
+{
+    let channel = world.resource::<RepairSessionChannel>().server;
+    let Some(info) = world
+        .resource_mut::<RepliconClient>()
+        .receive(channel)
+        .filter_map(|message| bincode::deserialize::<SessionInfo>(&message).ok())
+        .last()
+    else { return; };
+
+    let client_fingerprint = world.resource::<ReplicationFingerprint>().0;
+    let mut tracker = world.resource_mut::<ServerSessionTracker>();
+    let previous_epoch = tracker.epoch.replace(info.epoch);
+
+    if info.fingerprint != client_fingerprint
+    {
+        tracker.mismatched = true;
+        tracing::error!(server_fingerprint = info.fingerprint, client_fingerprint,
+            "replication protocol mismatch with server, discarding client session and blocking replication");
+        clear_server_session(world);
+        world.send_event(ProtocolMismatch{ server_fingerprint: info.fingerprint, client_fingerprint });
+        return;
+    }
+
+    tracker.received = true;
+
+    let Some(previous_epoch) = previous_epoch else { return; };
+    if previous_epoch == info.epoch { return; }
+    if !world.resource::<ActiveRepairConfig>().enabled { return; }
+    let state = *world.resource::<ClientRepairState>();
+    if state.not_in_state(ClientRepairState::Disconnected) && state.not_in_state(ClientRepairState::Waiting)
+    { return; }
+
+    clear_server_session(world);
+    world.send_event(SessionReset{
+        reason: SessionResetReason::ServerRestarted{ previous_epoch, epoch: info.epoch }
+    });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Discards all messages from the server after a replication protocol mismatch, since `ClientSet::Receive` is blocked
+/// and would otherwise let them accumulate.
+fn discard_blocked_messages(mut client: ResMut<RepliconClient>, channels: Res<RepliconChannels>)
+{
+    for channel_id in 0..channels.server_channels().len()
+    {
+        client.receive(channel_id as u8).for_each(drop);
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn protocol_mismatched(tracker: Option<Res<ServerSessionTracker>>) -> bool
+{
+    tracker.is_some_and(|t| t.mismatched)
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Replication messages are not applied until the server's session epoch is known, if session epochs are enabled.
+fn session_info_received(channel: Option<Res<RepairSessionChannel>>, tracker: Option<Res<ServerSessionTracker>>)
+    -> bool
+{
+    if channel.is_none() { return true; }
+    tracker.is_some_and(|t| t.received)
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
 /// Collects entities prespawned after starting to reconnect, in order to despawn entities spawned before that point.
 fn collect_prespawns_impl(
     In(collect)          : In<bool>,
@@ -88,54 +355,209 @@ fn collect_world_change_tick(world: &mut World)
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-fn initiate_just_disconnected(mut state: ResMut<ClientRepairState>)
+/// Only record replication writes in ticks where the first replication message might arrive.
+fn prepare_replication_tracker(state: Res<ClientRepairState>, mut tracker: ResMut<ReplicationTracker>)
+{
+    // keep recording until component repair is done, since it may be spread across multiple ticks
+    if state.in_state(ClientRepairState::Repairing) { return; }
+    // the first message can arrive in the tick where the client connects, while still `Disconnected`
+    tracker.reset(awaiting_reconnect(state));
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn clear_replication_tracker(mut tracker: ResMut<ReplicationTracker>)
 {
+    tracker.reset(false);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn initiate_just_disconnected(
+    mut state     : ResMut<ClientRepairState>,
+    replicon_tick : Res<ServerUpdateTick>,
+    mut events    : EventWriter<Disconnected>,
+){
     if state.in_state(ClientRepairState::Disconnected) { return; }
     state.set(ClientRepairState::Disconnected);
+    events.send(Disconnected{ tick: **replicon_tick });
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-fn initiate_waiting(mut state: ResMut<ClientRepairState>)
-{
+fn initiate_waiting(
+    mut state     : ResMut<ClientRepairState>,
+    replicon_tick : Res<ServerUpdateTick>,
+    mut events    : EventWriter<ReconnectWaiting>,
+){
     if state.in_state(ClientRepairState::Waiting) { return; }
     state.set(ClientRepairState::Waiting);
+    events.send(ReconnectWaiting{ tick: **replicon_tick });
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-fn initiate_repairing(mut state: ResMut<ClientRepairState>)
-{
+fn initiate_repairing(
+    mut state     : ResMut<ClientRepairState>,
+    mut counts    : ResMut<RepairCountsTracker>,
+    replicon_tick : Res<ServerUpdateTick>,
+    mut events    : EventWriter<RepairStarted>,
+){
     if state.in_state(ClientRepairState::Repairing) { return; }
     state.set(ClientRepairState::Repairing);
+    **counts = RepairCounts::default();
+    events.send(RepairStarted{ tick: **replicon_tick });
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-fn finish_repair(mut state: ResMut<ClientRepairState>)
-{
+fn finish_repair(
+    mut state     : ResMut<ClientRepairState>,
+    counts        : Res<RepairCountsTracker>,
+    replicon_tick : Res<ServerUpdateTick>,
+    mut events    : EventWriter<RepairFinished>,
+){
     if state.in_state(ClientRepairState::Done) { return; }
     state.set(ClientRepairState::Done);
+    events.send(RepairFinished{ tick: **replicon_tick, counts: **counts });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Mirrors the [`ClientRepairState`] resource into `State<ClientRepairState>`.
+fn sync_repair_state(
+    state    : Res<ClientRepairState>,
+    current  : Res<State<ClientRepairState>>,
+    mut next : ResMut<NextState<ClientRepairState>>,
+){
+    if **current == *state { return; }
+    next.set(*state);
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
 /// Iterate replicated entities after first init message, despawn entities with old replicon tick + remove from map.
+///
+/// Entities that were re-replicated are no longer [`Stale`].
 fn despawn_missing_entities(
     mut commands   : Commands,
-    replicated     : Query<(Entity, &ConfirmHistory), With<Replicated>>,
+    replicated     : Query<(Entity, &ConfirmHistory, Option<&RepairOverride>), With<Replicated>>,
+    stale          : Query<(), With<Stale>>,
     mut entity_map : ResMut<ServerEntityMap>,
+    mut counts     : ResMut<RepairCountsTracker>,
     replicon_tick  : Res<ServerUpdateTick>,
+    despawn_mode   : Res<RepairDespawnModeConfig>,
 ){
-    for (entity, history) in replicated.iter()
+    for (entity, history, repair_override) in replicated.iter()
     {
-        if history.last_tick() == **replicon_tick { continue; }
-        commands.get_entity(entity).map(DespawnRecursiveExt::despawn_recursive);
-        entity_map.remove_by_client(entity);
+        if history.last_tick() == **replicon_tick
+        {
+            if stale.contains(entity) { commands.entity(entity).remove::<Stale>(); }
+            continue;
+        }
+        let server_entity = entity_map.remove_by_client(entity);
+        let Some(mut entity_commands) = commands.get_entity(entity) else { continue; };
+
+        if repair_override.is_some_and(|o| o.never_despawn)
+        {
+            // remove Prespawned so the entity isn't cleaned up as a failed prespawn
+            entity_commands.remove::<(Replicated, Stale, Prespawned)>();
+            continue;
+        }
+
+        entity_commands.trigger(RepairDespawned{ entity, server_entity });
+        match **despawn_mode
+        {
+            RepairDespawnMode::Immediate =>
+            {
+                entity_commands.despawn_recursive();
+            }
+            RepairDespawnMode::Deferred(timeout) =>
+            {
+                entity_commands
+                    .remove::<Replicated>()
+                    .insert(RepairDespawning{ timer: Timer::new(timeout, TimerMode::Once) });
+            }
+        }
+        counts.despawned_entities += 1;
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Despawns ephemeral replicated entities when the client disconnects.
+fn despawn_ephemeral_entities(
+    mut commands   : Commands,
+    replicated     : Query<EntityRef, With<Replicated>>,
+    mut entity_map : ResMut<ServerEntityMap>,
+    ephemeral      : Res<EphemeralComponents>,
+){
+    for entity_ref in replicated.iter()
+    {
+        if !entity_ref.contains::<Ephemeral>() && !ephemeral.iter().any(|id| entity_ref.contains_id(*id))
+        { continue; }
+
+        let entity = entity_ref.id();
+        let server_entity = entity_map.remove_by_client(entity);
+        let Some(mut entity_commands) = commands.get_entity(entity) else { continue; };
+        entity_commands.trigger(RepairDespawned{ entity, server_entity });
+        entity_commands.despawn_recursive();
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Marks replicated entities as [`Stale`] when the client disconnects.
+fn mark_stale_entities(mut commands: Commands, replicated: Query<Entity, (With<Replicated>, Without<Stale>)>)
+{
+    for entity in replicated.iter()
+    {
+        commands.entity(entity).try_insert(Stale);
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Keeps [`LastConfirmedTick`] in sync with [`ConfirmHistory`].
+fn update_last_confirmed_ticks(
+    mut commands : Commands,
+    mut confirms : Query<(Entity, &ConfirmHistory, Option<&mut LastConfirmedTick>), Changed<ConfirmHistory>>,
+){
+    for (entity, history, last_confirmed) in confirms.iter_mut()
+    {
+        let tick = LastConfirmedTick(history.last_tick());
+        match last_confirmed
+        {
+            Some(mut last_confirmed) => { last_confirmed.set_if_neq(tick); }
+            None => { commands.entity(entity).try_insert(tick); }
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Despawns entities with [`RepairDespawning`] once their timeouts expire.
+fn despawn_expired_entities(
+    mut commands : Commands,
+    time         : Res<Time>,
+    mut pending  : Query<(Entity, &mut RepairDespawning)>,
+){
+    for (entity, mut despawning) in pending.iter_mut()
+    {
+        if !despawning.timer.tick(time.delta()).finished() { continue; }
+        let Some(entity_commands) = commands.get_entity(entity) else { continue; };
+        entity_commands.despawn_recursive();
     }
 }
 
@@ -160,43 +582,137 @@ fn clear_buffered_updates(mut buffered: ResMut<BufferedMutations>)
 
 /// Iterate prespawned entities, despawn if not replicated and not prespawned since this connection session
 /// started.
+///
+/// Replicated prespawns that were despawned by [`RepairDespawnMode::Deferred`] are already handled.
 fn despawn_failed_prespawns(
     mut commands : Commands,
     cached       : Res<CachedPrespawns>,
-    prespawned   : Query<(Entity, Has<Replicated>), With<Prespawned>>,
+    entity_map   : Res<ServerEntityMap>,
+    mut counts   : ResMut<RepairCountsTracker>,
+    prespawned   : Query<(Entity, Has<Replicated>, Has<RepairDespawning>), With<Prespawned>>,
 ){
-    for (entity, is_replicated) in prespawned.iter()
+    for (entity, is_replicated, is_despawning) in prespawned.iter()
     {
-        if is_replicated { continue; }
+        if is_replicated || is_despawning { continue; }
         if cached.contains(&entity) { continue; }
-        commands.get_entity(entity).map(DespawnRecursiveExt::despawn_recursive);
+        let server_entity = entity_map.to_server().get(&entity).copied();
+        let Some(mut entity_commands) = commands.get_entity(entity) else { continue; };
+        entity_commands.trigger(RepairDespawned{ entity, server_entity });
+        entity_commands.despawn_recursive();
+        counts.despawned_prespawns += 1;
     }
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-//todo: this could be more efficient...
-fn cleanup_entity_components(
-    mut commands : Commands,
-    replicated   : Query<Entity, With<Replicated>>,
-    preinit_tick : Res<RepairChangeTickTracker>,
-){
-    let preinit_tick = **preinit_tick;
-    for entity in replicated.iter()
-    {
-        commands.queue(
-            move |world: &mut World|
+/// Queues replicated entities that have components with repair rules.
+fn queue_component_repair(world: &mut World)
+{
+    let Some(replicated_id) = world.component_id::<Replicated>() else { return; };
+
+    world.resource_scope(|world, mut rules: Mut<ComponentRepairRules>| {
+        let mut pending = Vec::default();
+        for archetype in world.archetypes().iter()
+        {
+            if !archetype.contains(replicated_id) { continue; }
+            if !rules.applies_to(archetype) { continue; }
+            pending.extend(archetype.entities().iter().map(|e| e.id()));
+        }
+        world.resource_mut::<ComponentRepairQueue>().pending = pending;
+    });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Runs repair rules on queued entities until the queue is empty or the time budget is used up.
+fn repair_entity_components(world: &mut World, mut initial_components: Local<Vec<ComponentId>>)
+{
+    let start = Instant::now();
+    let preinit_tick = **world.resource::<RepairChangeTickTracker>();
+
+    world.resource_scope(|world, mut rules: Mut<ComponentRepairRules>| {
+        world.resource_scope(|world, mut queue: Mut<ComponentRepairQueue>| {
+            while let Some(entity) = queue.pending.pop()
             {
-                let rules = world.remove_resource::<ComponentRepairRules>().unwrap();
-                for rule in rules.iter()
+                let Ok(entity_ref) = world.get_entity(entity) else { continue; };
+                if !entity_ref.contains::<Replicated>() { continue; }
+                if entity_ref.get::<RepairOverride>().is_some_and(|o| o.retain_all) { continue; }
+                initial_components.clear();
+                initial_components.extend(entity_ref.archetype().components());
+
+                rules.repair(world, entity, preinit_tick);
+
+                // count components that are gone, so components inserted during repair are not subtracted
+                let removed = match world.get_entity(entity)
                 {
-                    let Ok(mut entity) = world.get_entity_mut(entity) else { return; };
-                    (*rule)(&mut entity, preinit_tick);
-                }
-                world.insert_resource(rules);
+                    Ok(entity_ref) => initial_components.iter().filter(|id| !entity_ref.contains_id(**id)).count(),
+                    Err(_)         => initial_components.len(),
+                };
+                let mut counts = world.resource_mut::<RepairCountsTracker>();
+                counts.repaired_entities += 1;
+                counts.removed_components += removed;
+
+                if queue.budget.is_some_and(|budget| start.elapsed() >= budget) { break; }
             }
-        );
+        });
+    });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+fn component_repair_done(queue: Res<ComponentRepairQueue>) -> bool
+{
+    queue.pending.is_empty()
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Despawns all client entities from the current server session and resets the repair lifecycle.
+fn forget_session(world: &mut World)
+{
+    clear_server_session(world);
+
+    let prespawns: Vec<Entity> = world.query_filtered::<Entity, With<Prespawned>>().iter(world).collect();
+    for entity in prespawns
+    {
+        let Ok(entity) = world.get_entity_mut(entity) else { continue; };
+        entity.despawn_recursive();
+    }
+
+    if let Some(mut cached) = world.get_resource_mut::<CachedPrespawns>() { cached.clear(); }
+    if let Some(mut queue) = world.get_resource_mut::<ComponentRepairQueue>() { queue.pending.clear(); }
+    if let Some(mut tracker) = world.get_resource_mut::<ReplicationTracker>() { tracker.reset(false); }
+    if let Some(mut tracker) = world.get_resource_mut::<ServerSessionTracker>() { tracker.epoch = None; }
+    if let Some(mut disconnected_at) = world.get_resource_mut::<DisconnectedAt>() { **disconnected_at = None; }
+    world.resource_mut::<ClientRepairState>().set(ClientRepairState::Dormant);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Extends [`Commands`] with client repair commands.
+pub trait ClientRepairCommands
+{
+    /// Forgets the current server session, e.g. after a deliberate logout or when switching accounts.
+    ///
+    /// Despawns all [`Replicated`], [`Prespawned`], and [`RepairDespawning`] entities, clears `ServerEntityMap` and
+    /// `bevy_replicon`'s other client state, and sets [`ClientRepairState`] to
+    /// [`Dormant`](ClientRepairState::Dormant). The next connection will be treated as an initial connection
+    /// instead of a reconnect.
+    ///
+    /// This should be used after disconnecting. No lifecycle events or [`RepairDespawned`] triggers are sent.
+    fn forget_session(&mut self);
+}
+
+impl ClientRepairCommands for Commands<'_, '_>
+{
+    fn forget_session(&mut self)
+    {
+        self.queue(forget_session);
     }
 }
 
@@ -207,7 +723,15 @@ fn cleanup_entity_components(
 ///
 /// The state will only leave `Dormant` after the first client disconnect. This ensures repair will not
 /// run needlessly for the first connection session.
-#[derive(Resource, Default, Debug, Hash, Eq, PartialEq, Copy, Clone)]
+///
+/// The resource is updated immediately by [`ClientRepairSet`] systems. It is also mirrored into Bevy's
+/// `State<ClientRepairState>` through `NextState` after [`ClientRepairSet::Finish`], so you can use `OnEnter`/`OnExit`
+/// schedules and the `in_state` run condition. The mirrored state only changes in `StateTransition`, so if the
+/// resource goes through multiple states in one tick (e.g. `Waiting -> Repairing -> Done`), only the final state is
+/// entered.
+///
+/// See also [`repair_just_finished`], [`is_repairing`], and [`awaiting_reconnect`].
+#[derive(Resource, States, Default, Debug, Hash, Eq, PartialEq, Copy, Clone)]
 pub enum ClientRepairState
 {
     /// The client is in its initial connection session.
@@ -217,7 +741,8 @@ pub enum ClientRepairState
     Disconnected,
     /// The client is connecting or connected but has not yet received its first replication message.
     Waiting,
-    /// Handling the first replication message.
+    /// Handling the first replication message. This may last multiple ticks if
+    /// [`ClientPlugin::repair_budget`] is set.
     Repairing,
     /// The first replication message has been handled.
     Done,
@@ -246,6 +771,32 @@ impl ClientRepairState
 
 //-------------------------------------------------------------------------------------------------------------------
 
+/// Run condition that returns `true` in the tick where [`ClientRepairState`] changes to
+/// [`ClientRepairState::Done`].
+pub fn repair_just_finished(state: Res<ClientRepairState>) -> bool
+{
+    state.is_changed() && state.in_state(ClientRepairState::Done)
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Run condition that returns `true` while [`ClientRepairState`] is [`ClientRepairState::Repairing`].
+pub fn is_repairing(state: Res<ClientRepairState>) -> bool
+{
+    state.in_state(ClientRepairState::Repairing)
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Run condition that returns `true` while [`ClientRepairState`] is [`ClientRepairState::Disconnected`] or
+/// [`ClientRepairState::Waiting`].
+pub fn awaiting_reconnect(state: Res<ClientRepairState>) -> bool
+{
+    state.in_state(ClientRepairState::Disconnected) || state.in_state(ClientRepairState::Waiting)
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
 /// Marker component for entities prespawned on a client that are expected to be replicated by the server.
 ///
 /// This component should be added to all prespawned entities that you want to be auto-cleaned up by
@@ -255,34 +806,124 @@ pub struct Prespawned;
 
 //-------------------------------------------------------------------------------------------------------------------
 
-/// System set in [`PreUpdate`] that contains all repair systems.
+/// Marker component inserted on replicated entities when the client disconnects.
+///
+/// It is removed from entities that are re-replicated in the first replication message after a reconnect, so while
+/// [`ClientRepairState`] is [`Disconnected`](ClientRepairState::Disconnected) or
+/// [`Waiting`](ClientRepairState::Waiting) it marks entities whose data may be out of date.
 ///
-/// Runs after [`ClientSet::Receive`].
-#[derive(SystemSet, Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
-pub struct ClientRepairSet;
+/// Entities that fail to re-replicate keep this component if [`ClientPlugin::despawn_mode`] is
+/// [`RepairDespawnMode::Deferred`].
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct Stale;
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Component on replicated client entities that records the last server tick confirmed for the entity.
+///
+/// Kept up to date with the entity's [`ConfirmHistory`] by [`ClientPlugin`].
+#[derive(Component, Debug, Deref, Copy, Clone, Eq, PartialEq)]
+pub struct LastConfirmedTick(RepliconTick);
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Component inserted on replicated entities that failed to re-replicate after a reconnect, when
+/// [`ClientPlugin::despawn_mode`] is [`RepairDespawnMode::Deferred`].
+///
+/// Entities with this component no longer have [`Replicated`] and are no longer in `ServerEntityMap`.
+/// They will be despawned recursively when the timeout expires. You can despawn them sooner yourself, for
+/// example when a fade-out effect is done.
+#[derive(Component, Debug)]
+pub struct RepairDespawning
+{
+    timer: Timer,
+}
+
+impl RepairDespawning
+{
+    /// Returns the time remaining until the entity is despawned.
+    pub fn remaining(&self) -> Duration
+    {
+        self.timer.remaining()
+    }
+
+    /// Returns the fraction of the timeout that has elapsed, in the range `[0.0, 1.0]`.
+    pub fn fraction(&self) -> f32
+    {
+        self.timer.fraction()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Controls how [`ClientPlugin`] despawns replicated entities that fail to re-replicate after a reconnect.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum RepairDespawnMode
+{
+    /// Entities are despawned recursively during repair.
+    #[default]
+    Immediate,
+    /// Entities are stripped of [`Replicated`] and their `ServerEntityMap` entries during repair, and a
+    /// [`RepairDespawning`] component is inserted. They will be despawned recursively after the timeout, or when
+    /// [`ClientRepairConfig::enabled`] is set to false and the client disconnects.
+    Deferred(Duration),
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// System sets in [`PreUpdate`] that contain all repair systems.
+///
+/// The sets run in the order they are declared, after [`ClientSet::Receive`] and before
+/// [`ClientSet::SyncHierarchy`].
+#[derive(SystemSet, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum ClientRepairSet
+{
+    /// Handles disconnects and reconnect attempts, updates [`LastConfirmedTick`], despawns expired
+    /// [`RepairDespawning`] entities, and starts repair when the first replication message after a reconnect arrives.
+    Prepare,
+    /// Despawns replicated entities that failed to re-replicate.
+    DespawnMissing,
+    /// Despawns [`Prespawned`] entities that failed to replicate, if [`ClientRepairConfig::cleanup_prespawns`] is set.
+    ///
+    /// Also contains the systems that collect new prespawned entities in [`Last`] while waiting for the first
+    /// replication message.
+    Prespawns,
+    /// Runs component repair rules. This may last multiple ticks if [`ClientPlugin::repair_budget`] is set.
+    Components,
+    /// Finishes repair once component repair is done.
+    Finish,
+}
 
 /// Adds client repair functionality to a client app that uses `bevy_replicon`.
 /// - Despawns replicated entities that fail to re-replicate after a reconnect.
 /// - Despawns [`Prespawned`] entities that fail to replicate after a reconnect (optional).
 /// - Runs custom component-removal systems on replicated entities after a reconnect.
+/// - Sends [`Disconnected`], [`ReconnectWaiting`], [`RepairStarted`], and [`RepairFinished`] events when
+///   [`ClientRepairState`] changes.
+/// - Despawns [`Ephemeral`] replicated entities as soon as the client disconnects.
+/// - Marks replicated entities [`Stale`] while they are waiting to be re-replicated after a disconnect.
+/// - Keeps [`LastConfirmedTick`] up to date on replicated entities.
+/// - Triggers [`RepairDespawned`] on entities despawned by repair.
 ///
 /// The goal of this plugin is to streamline client reconnects as much as possible by preserving existing client
 /// entities. There are a couple points to keep in mind:
 /// - After the client state is repaired, `Changed` filters will be triggered for replicated components that
 ///   use the default deserializer, even if a replicated component's value did not change on the server since before
-///   the reconnect.
-///   We remove dead replicated components by leveraging change detection, so with the current design triggering `Changed`
-///   is unavoidable.
+///   the reconnect. Register components with [`AppReplicationRepairExt::replicate_repair_eq`] to avoid this.
+/// - Components registered with [`AppReplicationRepairExt`] use write functions that record which components were
+///   replicated in the first replication message after a reconnect. If you override their command or marker
+///   functions, call [`record_replication_write`] in your write functions.
 /// - We allow you to register custom component-removal systems which will run on all replicated entities during repair.
 ///   This is a heavy-handed approach, because if a client adds a replicated component to a replicated entity in their
 ///   own system (e.g. they add `Transform` in reaction to a replicated blueprint, and also register `Transform` as
 ///   a component that can be replicated), then the component-removal systems may remove it from the entity erroneously.
-///   See [`repair_component`] for how to selectively disable component removal.
+///   See [`repair_component`] for how to selectively disable component removal, and [`RepairOverride`] for
+///   entity-level overrides.
 ///
 /// The `bevy_replicon` type [`ParentSync`] is automatically registered for repair if [`ParentSyncPlugin`] is present.
 ///
 /// This plugin must be added after `bevy_replicon`'s [`ClientPlugin`](bevy_replicon::prelude::ClientPlugin).
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ClientPlugin
 {
     /// This is used for cleaning up client entities that are pre-mapped on the server.
@@ -313,10 +954,25 @@ pub struct ClientPlugin
     ///   message.
     ///   For the best results, reinitialize your renet client between [`ClientSet::ReceivePackets`]
     ///   and [`ClientSet::Receive`] (in `PreUpdate`), and spawn prespawned
-    ///   entities after [`ClientRepairSet`] (which also runs in `PreUpdate`).
-    /// - If you spawn entities in schedule `Last`, do so before the [`ClientRepairSet`] otherwise we
+    ///   entities after [`ClientRepairSet::Finish`] (which also runs in `PreUpdate`).
+    /// - If you spawn entities in schedule `Last`, do so before [`ClientRepairSet::Prespawns`] otherwise we
     ///   won't track them for cleanup.
+    ///
+    /// This is the initial value of [`ClientRepairConfig::cleanup_prespawns`], which can be changed at runtime.
+    /// It is ignored if [`ClientRepairConfig`] is inserted before the plugin is added.
     pub cleanup_prespawns: bool,
+    /// Controls how replicated entities that fail to re-replicate after a reconnect are despawned.
+    ///
+    /// Defaults to [`RepairDespawnMode::Immediate`].
+    pub despawn_mode: RepairDespawnMode,
+    /// Max time to spend repairing components each tick.
+    ///
+    /// If set, component repair is spread across multiple ticks and [`ClientRepairState`] stays in
+    /// [`ClientRepairState::Repairing`] until all replicated entities have been repaired.
+    /// Entities are only despawned in the tick where the first replication message arrives.
+    ///
+    /// Defaults to `None`, which repairs all components in one tick.
+    pub repair_budget: Option<Duration>,
 }
 
 impl Plugin for ClientPlugin
@@ -326,34 +982,111 @@ impl Plugin for ClientPlugin
         if !app.is_plugin_added::<bevy_replicon::prelude::ClientPlugin>()
         { panic!("repair's ClientPlugin depends on replicon's ClientPlugin"); }
 
-        // disable replicon's cleanup
-        app.configure_sets(PreUpdate, ClientSet::Reset.run_if(|| false));
+        // disable replicon's cleanup unless repair is disabled for the session
+        app.configure_sets(PreUpdate, ClientSet::Reset.run_if(not(repair_enabled)));
+
+        // wait for the server's session epoch before applying replication
+        app.configure_sets(PreUpdate, ClientSet::Receive.run_if(session_info_received));
 
         // pre-register replicon's ParentSync
         if app.is_plugin_added::<ParentSyncPlugin>()
-        { app.add_replication_repair_fn(repair_component::<ParentSync>); }
-
-        // set up repair cleanup
-        let cleanup_prespawns = self.cleanup_prespawns;
+        {
+            track_replication::<ParentSync>(app);
+            app.add_replication_repair_policy(RepairPolicy::<ParentSync>::Remove);
+        }
 
-        if cleanup_prespawns
+        // set up runtime config
+        if !app.world().contains_resource::<ClientRepairConfig>()
         {
-            app.init_resource::<CachedPrespawns>();
+            app.insert_resource(ClientRepairConfig{ cleanup_prespawns: self.cleanup_prespawns, ..Default::default() });
         }
+        let config = *app.world().resource::<ClientRepairConfig>();
 
         if !app.world().contains_resource::<ComponentRepairRules>()
         { app.world_mut().init_resource::<ComponentRepairRules>(); }
 
-        app.init_resource::<ClientRepairState>()
+        if !app.world().contains_resource::<ReplicationTracker>()
+        { app.world_mut().init_resource::<ReplicationTracker>(); }
+
+        if !app.world().contains_resource::<EphemeralComponents>()
+        { app.world_mut().init_resource::<EphemeralComponents>(); }
+
+        if !app.is_plugin_added::<StatesPlugin>()
+        { app.add_plugins(StatesPlugin); }
+
+        app.init_resource::<CachedPrespawns>()
+            .insert_resource(ActiveRepairConfig(config))
+            .init_resource::<ClientRepairState>()
+            .init_state::<ClientRepairState>()
             .init_resource::<RepairChangeTickTracker>()
+            .init_resource::<RepairCountsTracker>()
+            .init_resource::<ServerSessionTracker>()
+            .init_resource::<DisconnectedAt>()
+            .insert_resource(RepairDespawnModeConfig(self.despawn_mode))
+            .insert_resource(ComponentRepairQueue{ budget: self.repair_budget, ..Default::default() })
+            .add_event::<Disconnected>()
+            .add_event::<ReconnectWaiting>()
+            .add_event::<RepairStarted>()
+            .add_event::<RepairFinished>()
+            .add_event::<SessionReset>()
+            .add_event::<ProtocolMismatch>()
+            .add_systems(PreStartup, init_replication_fingerprint)
             .configure_sets(PreUpdate,
-                ClientRepairSet
+                (
+                    ClientRepairSet::Prepare,
+                    ClientRepairSet::DespawnMissing,
+                    ClientRepairSet::Prespawns,
+                    ClientRepairSet::Components,
+                    ClientRepairSet::Finish,
+                )
+                    .chain()
                     .after(ClientSet::Receive)
                     .before(ClientSet::SyncHierarchy)
                     .run_if(resource_exists::<ServerUpdateTick>)
+                    .run_if(repair_enabled)
+            )
+            .add_systems(PreUpdate,
+                (
+                    // failed connection attempts also count as disconnects, so only capture the config when a
+                    // connection session ends
+                    apply_repair_config.run_if(not(awaiting_reconnect)),
+                    forget_session_epoch,
+                )
+                    .after(ClientSet::ReceivePackets)
+                    .before(ClientSet::Reset)
+                    .run_if(client_just_disconnected)
+            )
+            .add_systems(PreUpdate,
+                send_client_session_info
+                    .after(ClientSet::ReceivePackets)
+                    .run_if(resource_exists::<RepairSessionChannel>)
+                    .run_if(client_just_connected)
+            )
+            .add_systems(PreUpdate,
+                check_disconnect_duration
+                    .after(ClientSet::Reset)
+                    .before(ClientSet::Receive)
+                    .before(receive_session_info)
+                    .run_if(client_just_connected)
+            )
+            .add_systems(PreUpdate,
+                (
+                    receive_session_info,
+                    discard_blocked_messages.run_if(protocol_mismatched),
+                )
+                    .chain()
+                    .after(ClientSet::Reset)
+                    .before(ClientSet::Receive)
+                    .run_if(resource_exists::<RepairSessionChannel>)
+                    .run_if(client_connected)
             )
             .add_systems(PreUpdate,
-                collect_world_change_tick
+                (
+                    // keep the change tick from the first replication message while repair is ongoing
+                    collect_world_change_tick
+                        .run_if(|s: Res<ClientRepairState>| s.not_in_state(ClientRepairState::Repairing)),
+                    prepare_replication_tracker,
+                )
                     .after(ClientSet::ReceivePackets)
                     .before(ClientSet::Receive)
                     .run_if(|s: Res<ClientRepairState>| s.not_in_state(ClientRepairState::Dormant))
@@ -363,10 +1096,15 @@ impl Plugin for ClientPlugin
                     // state: -> Disconnected
                     (
                         clear_buffered_updates,
+                        despawn_ephemeral_entities,
+                        mark_stale_entities,
                         initiate_just_disconnected,
+                        record_disconnect_time.run_if(not(reset_strategy)),
+                        clear_server_session.run_if(reset_strategy),
                     )
                         .chain()
-                        .run_if(client_just_disconnected),
+                        .run_if(client_just_disconnected)
+                        .in_set(ClientRepairSet::Prepare),
                     // state: Disconnected -> Waiting
                     (
                         (
@@ -374,38 +1112,62 @@ impl Plugin for ClientPlugin
                             ignore_added_prespawns,
                         )
                             .chain()
-                            .run_if(move || cleanup_prespawns),
+                            .run_if(cleanup_prespawns_enabled),
                         initiate_waiting,
                     )
                         .chain()
                         .run_if(client_just_connected.or(client_connecting))
-                        .run_if(|s: Res<ClientRepairState>| s.in_state(ClientRepairState::Disconnected)),
+                        .run_if(|s: Res<ClientRepairState>| s.in_state(ClientRepairState::Disconnected))
+                        .in_set(ClientRepairSet::Prepare),
                     // state: Waiting -> Repairing
+                    // - Entity repair only runs in the tick where the first replication message arrives.
                     (
-                        initiate_repairing,
+                        initiate_repairing.in_set(ClientRepairSet::Prepare),
+                        despawn_missing_entities.in_set(ClientRepairSet::DespawnMissing),
+                        (
+                            collect_prespawns,  //we need to collect prespawns from this tick
+                            despawn_failed_prespawns,
+                            clear_prespawn_cache,
+                        )
+                            .chain()
+                            .run_if(cleanup_prespawns_enabled)
+                            .in_set(ClientRepairSet::Prespawns),
+                        queue_component_repair.in_set(ClientRepairSet::Components),
                     )
                         .chain()
                         .run_if(|s: Res<ClientRepairState>| s.in_state(ClientRepairState::Waiting))
                         .run_if(resource_changed::<ServerUpdateTick>),
-                    // repair
+                    // component repair
                     // state: Repairing -> Done
+                    // - Component repair may be spread across multiple ticks if there is a time budget.
                     (
-                        despawn_missing_entities,
+                        repair_entity_components.in_set(ClientRepairSet::Components),
                         (
-                            collect_prespawns,  //we need to collect prespawns from this tick
-                            despawn_failed_prespawns,
-                            clear_prespawn_cache,
+                            clear_replication_tracker,
+                            finish_repair,
                         )
                             .chain()
-                            .run_if(move || cleanup_prespawns),
-                        cleanup_entity_components,
-                        finish_repair,
+                            .run_if(component_repair_done)
+                            .in_set(ClientRepairSet::Finish),
                     )
                         .chain()
                         .run_if(|s: Res<ClientRepairState>| s.in_state(ClientRepairState::Repairing)),
                 )
                     .chain()
-                    .in_set(ClientRepairSet)
+            )
+            .add_systems(PreUpdate, sync_repair_state.after(ClientRepairSet::Finish))
+            .add_systems(PreUpdate,
+                // not gated by `repair_enabled` so confirmed ticks stay up to date if repair is disabled
+                update_last_confirmed_ticks
+                    .after(ClientSet::Receive)
+                    .before(ClientRepairSet::Prepare)
+            )
+            .add_systems(PreUpdate,
+                // despawn before repair so deferred despawns always survive at least one tick
+                // - not gated by `repair_enabled` so entities don't leak if repair is disabled
+                despawn_expired_entities
+                    .after(ClientSet::Receive)
+                    .before(ClientRepairSet::Prepare)
             )
             .add_systems(Last,
                 (
@@ -413,9 +1175,9 @@ impl Plugin for ClientPlugin
                     collect_prespawns,
                 )
                     .chain()
-                    .run_if(move || cleanup_prespawns)
+                    .run_if(cleanup_prespawns_enabled)
                     .run_if(|s: Res<ClientRepairState>| s.in_state(ClientRepairState::Waiting))
-                    .in_set(ClientRepairSet)
+                    .in_set(ClientRepairSet::Prespawns)
             );
     }
 }
@@ -0,0 +1,30 @@
+//local shortcuts
+
+//third-party shortcuts
+use bevy::ecs::component::ComponentId;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+//standard shortcuts
+
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Marker component for replicated client entities that should be despawned as soon as the client disconnects,
+/// instead of being repaired after a reconnect.
+///
+/// Useful for transient entities like projectiles and hit effects.
+///
+/// This can be inserted on the client (e.g. when reacting to a replicated blueprint), or replicated from the
+/// server if registered with `bevy_replicon`'s API on both client and server. See also
+/// [`AppReplicationRepairExt::add_ephemeral_component`](crate::AppReplicationRepairExt::add_ephemeral_component).
+#[derive(Component, Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Ephemeral;
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Components that make replicated entities ephemeral.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub(crate) struct EphemeralComponents(Vec<ComponentId>);
+
+//-------------------------------------------------------------------------------------------------------------------
@@ -0,0 +1,70 @@
+//local shortcuts
+use crate::*;
+
+//third-party shortcuts
+use bevy::ecs::component::Tick;
+use bevy::prelude::*;
+use bevy_replicon::core::replication::replication_rules::GroupReplication;
+use serde::{de::DeserializeOwned, Serialize};
+
+//standard shortcuts
+
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Component repair for component `C` in replication group `G`.
+///
+/// Mirrors [`repair_component`], except the component is also retained if the entity has a
+/// [`Retain<G>`](crate::Retain) component.
+pub fn repair_group_component<G, C>(entity: &mut EntityWorldMut, preinit_tick: Tick)
+where
+    G: Send + Sync + 'static,
+    C: Component,
+{
+    if is_retained::<G>(entity) { return; }
+    repair_component::<C>(entity, preinit_tick);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Registers [`repair_group_component::<G, C>`](repair_group_component) as the repair rule for `C`.
+///
+/// Intended for use in [`GroupRepair`] implementations.
+pub fn register_group_component_repair<G, C>(app: &mut App)
+where
+    G: Send + Sync + 'static,
+    C: Component,
+{
+    track_replication::<C>(app);
+    app.add_replication_repair_policy(RepairPolicy::<C>::Custom(repair_group_component::<G, C>));
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Replication groups that can register repair rules for each of their components.
+///
+/// Implemented for tuples of components. Custom [`GroupReplication`] implementations can implement this by calling
+/// [`register_group_component_repair`] for each component in the group.
+///
+/// See [`AppReplicationRepairExt::replicate_repair_group_components`].
+pub trait GroupRepair: GroupReplication + Send + Sync + 'static
+{
+    /// Registers repair rules for all components in the group.
+    fn register_repair(app: &mut App);
+}
+
+macro_rules! impl_group_repair {
+    ($($type:ident),*) => {
+        impl<$($type: Component + Serialize + DeserializeOwned),*> GroupRepair for ($($type,)*) {
+            fn register_repair(app: &mut App) {
+                $(
+                    register_group_component_repair::<Self, $type>(app);
+                )*
+            }
+        }
+    }
+}
+
+bevy::utils::all_tuples!(impl_group_repair, 1, 15, C);
+
+//-------------------------------------------------------------------------------------------------------------------
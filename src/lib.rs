@@ -6,14 +6,28 @@ use crate as bevy_replicon_repair;
 
 //module tree
 mod app_ext;
+mod client_config;
+mod client_events;
 mod client_plugin;
+mod ephemeral;
+mod group_repair;
+mod repair_policy;
 mod repair_rules;
+mod replication_tracker;
 mod retain;
 mod server_plugin;
+mod session;
 
 //API exports
 pub use crate::app_ext::*;
+pub use crate::client_config::*;
+pub use crate::client_events::*;
 pub use crate::client_plugin::*;
+pub use crate::ephemeral::*;
+pub use crate::group_repair::*;
+pub use crate::repair_policy::*;
 pub use crate::repair_rules::*;
+pub use crate::replication_tracker::*;
 pub use crate::retain::*;
 pub use crate::server_plugin::*;
+pub use crate::session::*;
@@ -0,0 +1,89 @@
+//local shortcuts
+use crate::*;
+
+//third-party shortcuts
+use bevy::ecs::component::Tick;
+use bevy::prelude::*;
+
+//standard shortcuts
+
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Component repair that replaces `C` with `C::default()` if the component was not replicated to the entity in the
+/// first replication message after a reconnect.
+///
+/// Does nothing if the entity does not have `C` or `C` is retained. See [`is_retained`].
+///
+/// Triggers [`RepairRemoved<C>`](crate::RepairRemoved) on the entity with the replaced component value.
+pub fn reset_component<C: Component + Default>(entity: &mut EntityWorldMut, preinit_tick: Tick)
+{
+    replace_component::<C>(entity, preinit_tick, C::default);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+fn replace_component<C: Component>(entity: &mut EntityWorldMut, preinit_tick: Tick, replacement: fn() -> C)
+{
+    if is_retained::<C>(entity) { return; }
+    if !entity.contains::<C>() { return; }
+    if component_was_replicated::<C>(entity, preinit_tick) { return; }
+
+    let Some(mut component) = entity.get_mut::<C>() else { return; };
+    let value = std::mem::replace(&mut *component, (replacement)());
+    let entity_id = entity.id();
+    entity.trigger(RepairRemoved{ entity: entity_id, value });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Declarative repair behavior for replicated component `C`, used by [`AppReplicationRepairExt`].
+///
+/// Use [`RepairPolicy::reset_to_default`] to reset components to their default values. There is no `ResetToDefault`
+/// variant because it would require `C: Default` for every policy.
+#[derive(Debug)]
+pub enum RepairPolicy<C: Component>
+{
+    /// Remove `C` if it was not replicated after a reconnect. See [`repair_component`].
+    Remove,
+    /// Never remove `C` during repair.
+    Retain,
+    /// Replace `C` with the returned value if it was not replicated after a reconnect. Triggers
+    /// [`RepairRemoved<C>`](crate::RepairRemoved) with the replaced value.
+    ReplaceWith(fn() -> C),
+    /// Use a custom repair function. It will only be called on entities that have `C`.
+    Custom(RepairComponentFn),
+}
+
+impl<C: Component> RepairPolicy<C>
+{
+    /// Makes a policy that resets `C` to `C::default()` if it was not replicated after a reconnect.
+    /// See [`reset_component`].
+    pub fn reset_to_default() -> Self
+    where
+        C: Default
+    {
+        Self::ReplaceWith(C::default)
+    }
+
+    /// Converts the policy to a repair function. Returns `None` if there is nothing to repair.
+    pub(crate) fn into_repair_fn(self) -> Option<BoxedRepairFn>
+    {
+        match self
+        {
+            Self::Remove            => Some(Box::new(repair_component::<C>)),
+            Self::Retain            => None,
+            Self::ReplaceWith(func) => Some(Box::new(move |e: &mut EntityWorldMut, t| replace_component(e, t, func))),
+            Self::Custom(func)      => Some(Box::new(func)),
+        }
+    }
+}
+
+impl<C: Component> Clone for RepairPolicy<C>
+{
+    fn clone(&self) -> Self { *self }
+}
+
+impl<C: Component> Copy for RepairPolicy<C> {}
+
+//-------------------------------------------------------------------------------------------------------------------
@@ -1,10 +1,14 @@
 //local shortcuts
 
 //third-party shortcuts
-use bevy::ecs::component::Tick;
+use bevy::ecs::archetype::{Archetype, ArchetypeId};
+use bevy::ecs::component::{ComponentId, Tick};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 
 //standard shortcuts
+use std::any::{type_name, TypeId};
+use std::borrow::Cow;
 
 
 //-------------------------------------------------------------------------------------------------------------------
@@ -12,16 +16,209 @@ use bevy::prelude::*;
 /// Signature of component repair functions.
 ///
 /// We pass in a world change tick from before the first server init message for the current session.
-/// This can be used to detect component changes caused by replication, which indicates a component was replicated.
+/// Use [`component_was_replicated`](crate::component_was_replicated) to check if a component was replicated.
 ///
 /// See [`repair_component`](crate::repair_component) for the default implementation.
 pub type RepairComponentFn = fn(&mut EntityWorldMut, Tick);
 
 //-------------------------------------------------------------------------------------------------------------------
 
-#[derive(Resource, Deref, DerefMut)]
-pub(crate) struct ComponentRepairRules(Vec<RepairComponentFn>);
+/// Type-erased component repair function.
+pub(crate) type BoxedRepairFn = Box<dyn FnMut(&mut EntityWorldMut, Tick) + Send + Sync + 'static>;
 
-impl Default for ComponentRepairRules { fn default() -> Self { Self(Vec::default()) } }
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Information about a component with a registered repair rule.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RepairRuleInfo
+{
+    /// The component's id in the app's world.
+    pub component_id: ComponentId,
+    /// The component's type id.
+    pub type_id: TypeId,
+    /// The component's type name.
+    pub type_name: &'static str,
+}
+
+impl RepairRuleInfo
+{
+    pub(crate) fn new<C: Component>(world: &mut World) -> Self
+    {
+        Self{
+            component_id: world.register_component::<C>(),
+            type_id: TypeId::of::<C>(),
+            type_name: type_name::<C>(),
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+enum RepairRuleKind
+{
+    /// Applied to replicated entities that have the component.
+    Typed(RepairRuleInfo),
+    /// Applied to all replicated entities.
+    Untyped(RepairComponentFn),
+    /// Repair system applied to all replicated entities.
+    System(Cow<'static, str>),
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+struct RepairRule
+{
+    kind: RepairRuleKind,
+    /// `None` if the component is never repaired (e.g. [`RepairPolicy::Retain`](crate::RepairPolicy::Retain)).
+    repair: Option<BoxedRepairFn>,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Registry of component repair rules.
+///
+/// Rules are registered with [`AppReplicationRepairExt`](crate::AppReplicationRepairExt). Use
+/// [`AppReplicationRepairExt::repair_rules`](crate::AppReplicationRepairExt::repair_rules) or access this resource
+/// directly to inspect them.
+///
+/// Each component can have at most one rule. Registering a new rule for a component replaces the previous rule.
+#[derive(Resource, Default)]
+pub struct ComponentRepairRules
+{
+    rules: Vec<RepairRule>,
+    /// [ component type : index of rule ]
+    typed: HashMap<TypeId, usize>,
+    /// [ archetype : indices of rules that apply to the archetype ]
+    archetype_rules: HashMap<ArchetypeId, Vec<usize>>,
+}
+
+impl ComponentRepairRules
+{
+    /// Returns `true` if component `C` has a repair rule.
+    pub fn contains<C: Component>(&self) -> bool
+    {
+        self.typed.contains_key(&TypeId::of::<C>())
+    }
+
+    /// Returns `true` if the component has a repair rule.
+    pub fn contains_id(&self, component_id: ComponentId) -> bool
+    {
+        self.iter().any(|info| info.component_id == component_id)
+    }
+
+    /// Iterates components with repair rules.
+    ///
+    /// Does not include rules registered with
+    /// [`add_replication_repair_fn`](crate::AppReplicationRepairExt::add_replication_repair_fn) or
+    /// [`replicate_repair_group`](crate::AppReplicationRepairExt::replicate_repair_group), or
+    /// [`add_replication_repair_system`](crate::AppReplicationRepairExt::add_replication_repair_system), which are
+    /// not associated with a specific component.
+    pub fn iter(&self) -> impl Iterator<Item = &RepairRuleInfo> + '_
+    {
+        self.rules
+            .iter()
+            .filter_map(|rule| match &rule.kind { RepairRuleKind::Typed(info) => Some(info), _ => None })
+    }
+
+    /// Iterates the names of repair systems registered with
+    /// [`add_replication_repair_system`](crate::AppReplicationRepairExt::add_replication_repair_system).
+    pub fn system_names(&self) -> impl Iterator<Item = &str> + '_
+    {
+        self.rules
+            .iter()
+            .filter_map(|rule| match &rule.kind { RepairRuleKind::System(name) => Some(name.as_ref()), _ => None })
+    }
+
+    /// Returns the number of repair functions and repair systems that are not associated with a specific component.
+    pub fn untyped_len(&self) -> usize
+    {
+        self.rules.iter().filter(|rule| !matches!(rule.kind, RepairRuleKind::Typed(_))).count()
+    }
+
+    /// Adds a rule for a specific component. Replaces the existing rule for the component if there is one.
+    pub(crate) fn add_typed(&mut self, info: RepairRuleInfo, repair: Option<BoxedRepairFn>)
+    {
+        self.archetype_rules.clear();
+        let rule = RepairRule{ kind: RepairRuleKind::Typed(info), repair };
+
+        if let Some(index) = self.typed.get(&info.type_id)
+        {
+            tracing::warn!("replacing existing repair rule for {}", info.type_name);
+            self.rules[*index] = rule;
+            return;
+        }
+
+        self.typed.insert(info.type_id, self.rules.len());
+        self.rules.push(rule);
+    }
+
+    /// Adds a rule that applies to all replicated entities. Ignores duplicate functions.
+    pub(crate) fn add_untyped(&mut self, repair: RepairComponentFn)
+    {
+        let duplicate = self.rules
+            .iter()
+            .any(|rule| matches!(rule.kind, RepairRuleKind::Untyped(f) if f as usize == repair as usize));
+        if duplicate
+        {
+            tracing::warn!("ignoring duplicate repair function registration");
+            return;
+        }
+
+        self.archetype_rules.clear();
+        self.rules.push(RepairRule{ kind: RepairRuleKind::Untyped(repair), repair: Some(Box::new(repair)) });
+    }
+
+    /// Adds a repair system that applies to all replicated entities.
+    pub(crate) fn add_system(&mut self, name: Cow<'static, str>, repair: BoxedRepairFn)
+    {
+        self.archetype_rules.clear();
+        self.rules.push(RepairRule{ kind: RepairRuleKind::System(name), repair: Some(repair) });
+    }
+
+    /// Returns `true` if any rule applies to entities in the archetype.
+    pub(crate) fn applies_to(&mut self, archetype: &Archetype) -> bool
+    {
+        !self.archetype_rules(archetype).is_empty()
+    }
+
+    /// Runs all rules that apply to the entity.
+    pub(crate) fn repair(&mut self, world: &mut World, entity: Entity, preinit_tick: Tick)
+    {
+        let Ok(entity_ref) = world.get_entity(entity) else { return; };
+        let archetype = entity_ref.archetype();
+        self.archetype_rules(archetype);
+        let Some(indices) = self.archetype_rules.get(&archetype.id()) else { return; };
+
+        for index in indices.iter()
+        {
+            let Ok(mut entity) = world.get_entity_mut(entity) else { return; };
+            let Some(repair) = &mut self.rules[*index].repair else { continue; };
+            (repair)(&mut entity, preinit_tick);
+        }
+    }
+
+    fn archetype_rules(&mut self, archetype: &Archetype) -> &Vec<usize>
+    {
+        self.archetype_rules
+            .entry(archetype.id())
+            .or_insert_with(
+                ||
+                self.rules
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, rule)| rule.repair.is_some())
+                    .filter(
+                        |(_, rule)| match &rule.kind
+                        {
+                            RepairRuleKind::Typed(info) => archetype.contains(info.component_id),
+                            RepairRuleKind::Untyped(_) |
+                            RepairRuleKind::System(_)   => true,
+                        }
+                    )
+                    .map(|(index, _)| index)
+                    .collect()
+            )
+    }
+}
 
 //-------------------------------------------------------------------------------------------------------------------
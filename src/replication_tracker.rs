@@ -0,0 +1,173 @@
+//local shortcuts
+
+//third-party shortcuts
+use bevy::ecs::component::{ComponentId, Tick};
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_replicon::core::replication::deferred_entity::DeferredEntity;
+use bevy_replicon::core::replication::replication_registry::command_fns::{default_remove, default_write, WriteFn};
+use bevy_replicon::core::replication::replication_registry::ctx::WriteCtx;
+use bevy_replicon::core::replication::replication_registry::rule_fns::RuleFns;
+use bevy_replicon::prelude::AppMarkerExt;
+
+//standard shortcuts
+use std::io::Cursor;
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Records which components were written by replication in the first replication message after a reconnect.
+#[derive(Resource, Default)]
+pub(crate) struct ReplicationTracker
+{
+    /// Components whose write functions record writes.
+    tracked: HashSet<ComponentId>,
+    /// Whether writes are currently being recorded.
+    active: bool,
+    /// [ client entity : components written ]
+    received: EntityHashMap<HashSet<ComponentId>>,
+}
+
+impl ReplicationTracker
+{
+    /// Clears recorded writes and sets whether new writes should be recorded.
+    pub(crate) fn reset(&mut self, active: bool)
+    {
+        self.active = active;
+        self.received.clear();
+    }
+
+    /// Returns `None` if writes of the component are not tracked.
+    fn was_received(&self, entity: Entity, component_id: ComponentId) -> Option<bool>
+    {
+        if !self.tracked.contains(&component_id) { return None; }
+        Some(self.received.get(&entity).is_some_and(|c| c.contains(&component_id)))
+    }
+
+    fn record(&mut self, entity: Entity, component_id: ComponentId)
+    {
+        self.received.entry(entity).or_default().insert(component_id);
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Installs [`tracked_write`] as the default write function for `C`.
+pub(crate) fn track_replication<C: Component>(app: &mut App)
+{
+    track_replication_with::<C>(app, tracked_write::<C>);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Installs [`tracked_write_eq`] as the default write function for `C`.
+pub(crate) fn track_replication_eq<C: Component + PartialEq>(app: &mut App)
+{
+    track_replication_with::<C>(app, tracked_write_eq::<C>);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+fn track_replication_with<C: Component>(app: &mut App, write: WriteFn<C>)
+{
+    if !app.world().contains_resource::<ReplicationTracker>()
+    { app.world_mut().init_resource::<ReplicationTracker>(); }
+
+    app.set_command_fns::<C>(write, default_remove::<C>);
+    let component_id = app.world_mut().register_component::<C>();
+    app.world_mut().resource_mut::<ReplicationTracker>().tracked.insert(component_id);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Replicon write function that records the write for repair then defers to
+/// [`default_write`](bevy_replicon::core::replication::replication_registry::command_fns::default_write).
+fn tracked_write<C: Component>(
+    ctx      : &mut WriteCtx,
+    rule_fns : &RuleFns<C>,
+    entity   : &mut DeferredEntity,
+    cursor   : &mut Cursor<&[u8]>,
+) -> bincode::Result<()>
+{
+    record_replication_write(ctx, entity);
+    default_write(ctx, rule_fns, entity, cursor)
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Replicon write function that records the write for repair. While recording writes for repair, existing components
+/// are only overwritten if the new value differs from the old value, which preserves change detection for unchanged
+/// components.
+///
+/// Otherwise defers to [`default_write`](bevy_replicon::core::replication::replication_registry::command_fns::default_write).
+fn tracked_write_eq<C: Component + PartialEq>(
+    ctx      : &mut WriteCtx,
+    rule_fns : &RuleFns<C>,
+    entity   : &mut DeferredEntity,
+    cursor   : &mut Cursor<&[u8]>,
+) -> bincode::Result<()>
+{
+    record_replication_write(ctx, entity);
+    if !is_recording(entity) || !entity.contains::<C>()
+    { return default_write(ctx, rule_fns, entity, cursor); }
+
+    let new_value: C = rule_fns.deserialize(ctx, cursor)?;
+    if let Some(mut component) = entity.get_mut::<C>()
+    {
+        component.set_if_neq(new_value);
+    }
+
+    Ok(())
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+fn is_recording(entity: &DeferredEntity) -> bool
+{
+    entity.world().get_resource::<ReplicationTracker>().is_some_and(|t| t.active)
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Records that the component in `ctx` was written to `entity` by replication.
+///
+/// Components registered with [`AppReplicationRepairExt`](crate::AppReplicationRepairExt) use write functions that
+/// call this automatically. If you override the command functions or marker functions of a component registered for
+/// repair, then your write functions must call this, otherwise repair will assume the component failed to replicate.
+pub fn record_replication_write(ctx: &mut WriteCtx, entity: &DeferredEntity)
+{
+    if !is_recording(entity) { return; }
+
+    let entity = entity.id();
+    let component_id = ctx.component_id;
+    ctx.commands.queue(
+        move |world: &mut World|
+        {
+            world.resource_mut::<ReplicationTracker>().record(entity, component_id);
+        }
+    );
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Returns `true` if component `C` on `entity` was written by the first replication message after a reconnect.
+///
+/// For components registered with [`AppReplicationRepairExt`](crate::AppReplicationRepairExt), this uses writes
+/// recorded by replication. Other components fall back to checking if the component was added/changed since
+/// `preinit_tick`, which is imprecise if replication skips change detection or if your systems change the component
+/// in the repair tick.
+///
+/// Intended for use in [`RepairComponentFn`](crate::RepairComponentFn) implementations.
+pub fn component_was_replicated<C: Component>(entity: &EntityWorldMut, preinit_tick: Tick) -> bool
+{
+    let world = entity.world();
+    let received = world.component_id::<C>()
+        .zip(world.get_resource::<ReplicationTracker>())
+        .and_then(|(component_id, tracker)| tracker.was_received(entity.id(), component_id));
+    if let Some(received) = received { return received; }
+
+    let Some(change_ticks) = entity.get_change_ticks::<C>() else { return false; };
+    change_ticks.is_changed(preinit_tick, world.read_change_tick())
+}
+
+//-------------------------------------------------------------------------------------------------------------------
@@ -10,10 +10,46 @@ use std::marker::PhantomData;
 
 /// Marker component for client entities that prevents component removal during reconnect repair.
 ///
+/// `T` can be a component, or a replication group registered with
+/// [`replicate_repair_group_components`](crate::AppReplicationRepairExt::replicate_repair_group_components) to retain
+/// all components in the group.
+///
 /// See [`repair_component`](crate::repair_component).
 #[derive(Component)]
 pub struct Retain<T>(PhantomData<T>);
 
-impl<T> Default for Retain<T> { fn default() -> Self { Self(PhantomData::default()) } }
+impl<T> Default for Retain<T> { fn default() -> Self { Self(PhantomData) } }
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Component for client entities that overrides reconnect repair for the whole entity.
+///
+/// Checked by [`ClientPlugin`](crate::ClientPlugin) before applying per-component repair rules.
+#[derive(Component, Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct RepairOverride
+{
+    /// Skip component repair for this entity. Takes precedence over [`Self::always_reset`].
+    pub retain_all: bool,
+    /// If the entity fails to re-replicate, don't despawn it. Instead, [`Replicated`](bevy_replicon::prelude::Replicated)
+    /// and its `ServerEntityMap` entry will be removed so the entity becomes a normal client entity.
+    /// [`Prespawned`](crate::Prespawned) is also removed.
+    pub never_despawn: bool,
+    /// Apply component repair even if the entity has [`Retain<C>`] markers.
+    pub always_reset: bool,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Returns `true` if component `C` on `entity` should be retained because of [`Retain<C>`], taking
+/// [`RepairOverride::always_reset`] into account.
+///
+/// `C` can also be a replication group (see [`Retain`]).
+///
+/// Intended for use in [`RepairComponentFn`](crate::RepairComponentFn) implementations.
+pub fn is_retained<C: Send + Sync + 'static>(entity: &EntityWorldMut) -> bool
+{
+    if !entity.contains::<Retain<C>>() { return false; }
+    !entity.get::<RepairOverride>().is_some_and(|o| o.always_reset)
+}
 
 //-------------------------------------------------------------------------------------------------------------------
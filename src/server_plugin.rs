@@ -3,19 +3,208 @@ use crate::*;
 
 //third-party shortcuts
 use bevy::prelude::*;
-use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::entity::{EntityHashMap, EntityHashSet};
+use bevy::ecs::event::EventCursor;
+use bevy::ecs::system::BoxedSystem;
+use bevy::utils::{HashMap, Instant};
 use bevy_replicon::prelude::*;
+use bevy_replicon::core::replicon_server::RepliconServer;
+use bevy_replicon::core::replicon_tick::RepliconTick;
 use bevy_replicon::server::server_tick::ServerTick;
 
 //standard shortcuts
+use std::time::Duration;
 
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-/// [ server entity : (client id : client entity) ]
+/// Event sent by [`ServerPlugin`] when a client's [`replication_fingerprint`] does not match the server's.
+///
+/// The client and server registered different replicated components (or registered them in a different order). The
+/// client will not apply replication messages for the rest of the connection (see [`ProtocolMismatch`]), so you
+/// should disconnect it.
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ClientProtocolMismatch
+{
+    /// The mismatched client.
+    pub client_id: ClientId,
+    /// The server's fingerprint.
+    pub server_fingerprint: u64,
+    /// The client's fingerprint.
+    pub client_fingerprint: u64,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Persistent identity of a client across connections, e.g. an account id or a hash of a session token.
+///
+/// See [`AppReplicationRepairExt::set_client_identity_resolver`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ClientIdentity(pub u64);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// User-defined system that resolves the [`ClientIdentity`] of newly connected clients.
+#[derive(Resource)]
+struct ClientIdentityResolver(BoxedSystem<In<ClientId>, Option<ClientIdentity>>);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// [ client id : identity ]
+///
+/// Entries are kept after clients disconnect so their cached mappings can be rebound when they reconnect.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct ClientIdentities(HashMap<ClientId, ClientIdentity>);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Controls when cached client entity mappings of disconnected clients are discarded.
+///
+/// See [`ServerPlugin::mapping_expiry`].
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum MappingExpiry
+{
+    /// Cached mappings are kept until their server entities are despawned.
+    #[default]
+    Never,
+    /// Cached mappings are discarded once their client has been disconnected for this long.
+    Duration(Duration),
+    /// Cached mappings are discarded once their client has been disconnected for this many server ticks.
+    Ticks(u32),
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ServerPlugin`] when cached client entity mappings are discarded because they expired (see
+/// [`ServerPlugin::mapping_expiry`]), because the client exceeded [`ServerPlugin::max_cached_mappings`], or because the
+/// client was forgotten (see [`ServerPlugin::max_session_records`]).
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ClientMappingsExpired
+{
+    /// The client whose mappings were discarded.
+    pub client_id: ClientId,
+    /// The number of mappings discarded.
+    pub count: usize,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Event sent by [`ServerPlugin`] when a client that was previously connected reconnects.
+///
+/// This is sent in [`ServerRepairSet::Restore`] after `bevy_replicon`'s `ServerEvent::ClientConnected`, instead of for
+/// every connection. A client is returning if it connected before with the same `ClientId` (or the same
+/// [`ClientIdentity`], see [`AppReplicationRepairExt::set_client_identity_resolver`]) and its cached mappings have
+/// not expired (see [`ServerPlugin::mapping_expiry`] and [`ServerPlugin::max_session_records`]).
+#[derive(Event, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ClientReconnected
+{
+    /// The reconnected client.
+    pub client_id: ClientId,
+    /// The number of cached client entity mappings returned to the client.
+    pub restored_mappings: usize,
+    /// How long the client was disconnected.
+    pub offline_duration: Duration,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+#[derive(Resource, Debug, Copy, Clone)]
+struct CachedMappingConfig
+{
+    expiry: MappingExpiry,
+    max_per_client: Option<usize>,
+    max_records: Option<usize>,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Copy, Clone)]
+struct CachedMapping
+{
+    client_entity: Entity,
+    /// Order in which the mapping was cached, used to discard the oldest mappings first.
+    sequence: u64,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Each client can have one mapping per server entity, so multiple clients can map the same server entity (e.g. for
+/// shared prespawns).
+#[derive(Resource, Default)]
+struct CachedClientMap
+{
+    /// [ client id : [ server entity : mapping ] ]
+    clients: HashMap<ClientId, EntityHashMap<CachedMapping>>,
+    next_sequence: u64,
+}
+
+impl CachedClientMap
+{
+    /// Returns the previous mapping of the server entity for the client, if any.
+    fn insert(&mut self, client_id: ClientId, server_entity: Entity, client_entity: Entity) -> Option<CachedMapping>
+    {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.clients
+            .entry(client_id)
+            .or_default()
+            .insert(server_entity, CachedMapping{ client_entity, sequence })
+    }
+
+    /// Removes all mappings of the client. Returns the number of mappings removed.
+    fn remove_client(&mut self, client_id: ClientId) -> usize
+    {
+        self.clients.remove(&client_id).map(|mappings| mappings.len()).unwrap_or_default()
+    }
+
+    /// Moves all mappings of `from` to `to`. Mappings of `to` take precedence.
+    fn rebind_client(&mut self, from: ClientId, to: ClientId)
+    {
+        let Some(previous) = self.clients.remove(&from) else { return; };
+        let mappings = self.clients.entry(to).or_default();
+        for (server_entity, mapping) in previous
+        {
+            mappings.entry(server_entity).or_insert(mapping);
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Server entities that lost [`Replicated`] and may have cached mappings.
 #[derive(Resource, Default, Deref, DerefMut)]
-struct CachedClientMap(EntityHashMap<(ClientId, Entity)>);
+struct PendingMappingEvictions(EntityHashSet);
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Default, Copy, Clone)]
+struct ClientSessionRecord
+{
+    /// (time of disconnect, server tick at disconnect) if the client is disconnected.
+    disconnected: Option<(Instant, RepliconTick)>,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// [ client id : record ] for clients that connected at least once.
+///
+/// Records of disconnected clients are removed when their cached mappings expire, or when there are more than
+/// [`ServerPlugin::max_session_records`].
+#[derive(Resource, Default, Deref, DerefMut)]
+struct ClientSessionRecords(HashMap<ClientId, ClientSessionRecord>);
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
@@ -26,10 +215,12 @@ fn collect_client_map(mut cached: ResMut<CachedClientMap>, mapped: Res<ClientEnt
     {
         for mapping in mappings.iter()
         {
-            // only one server <-> client entity mapping is currently supported per server entity
-            // - Note: This warning will print if `ClientEntityMap` entries are inserted before `ServerSet::Receive`.
-            if cached.insert(mapping.server_entity, (*client_id, mapping.client_entity)).is_some()
-            { tracing::warn!(?client_id, ?mapping, "overwriting cached client mapping"); }
+            // only one server <-> client entity mapping is supported per client per server entity
+            let Some(previous) = cached.insert(*client_id, mapping.server_entity, mapping.client_entity)
+            else { continue; };
+            if previous.client_entity == mapping.client_entity { continue; }
+            tracing::warn!(?client_id, ?mapping, previous_client_entity = ?previous.client_entity,
+                "overwriting cached client mapping");
         }
     }
 }
@@ -37,64 +228,399 @@ fn collect_client_map(mut cached: ResMut<CachedClientMap>, mapped: Res<ClientEnt
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
+/// Resolves the identities of newly connected clients, and moves cached mappings from previous client ids with the
+/// same identity to the new client ids.
+fn rebind_client_identities(world: &mut World, mut cursor: Local<EventCursor<ServerEvent>>)
+{
+    let connected: Vec<ClientId> = cursor
+        .read(world.resource::<Events<ServerEvent>>())
+        .filter_map(|event| match event { ServerEvent::ClientConnected{ client_id } => Some(*client_id), _ => None })
+        .collect();
+    if connected.is_empty() { return; }
+
+    world.resource_scope(
+        |world, mut resolver: Mut<ClientIdentityResolver>|
+        {
+            for client_id in connected
+            {
+                let Some(identity) = resolver.0.run(client_id, world) else { continue; };
+
+                let mut identities = world.resource_mut::<ClientIdentities>();
+                let previous: Vec<ClientId> = identities
+                    .iter()
+                    .filter(|(id, i)| **i == identity && **id != client_id)
+                    .map(|(id, _)| *id)
+                    .collect();
+                for previous_id in previous.iter()
+                {
+                    identities.remove(previous_id);
+                }
+                identities.insert(client_id, identity);
+
+                // the new client id inherits the most recent session record
+                let mut records = world.resource_mut::<ClientSessionRecords>();
+                let previous_record = previous
+                    .iter()
+                    .filter_map(|previous_id| records.remove(previous_id))
+                    .max_by_key(|record| record.disconnected.map(|(disconnected_at, _)| disconnected_at));
+                if let Some(record) = previous_record
+                {
+                    records.entry(client_id).or_insert(record);
+                }
+
+                let mut cached = world.resource_mut::<CachedClientMap>();
+                for previous_id in previous
+                {
+                    tracing::trace!(?identity, ?previous_id, ?client_id,
+                        "rebinding cached client mappings to reconnected client");
+                    cached.rebind_client(previous_id, client_id);
+                }
+            }
+        }
+    );
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Sets the system used to resolve [`ClientIdentity`]s.
+pub(crate) fn set_client_identity_resolver<M>(
+    app    : &mut App,
+    system : impl IntoSystem<In<ClientId>, Option<ClientIdentity>, M>,
+){
+    let mut system = IntoSystem::into_system(system);
+    system.initialize(app.world_mut());
+    app.insert_resource(ClientIdentityResolver(Box::new(system)));
+
+    if !app.world().contains_resource::<ClientIdentities>()
+    { app.world_mut().init_resource::<ClientIdentities>(); }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Returns cached mappings to connected clients and sends [`ClientReconnected`] for returning clients.
 fn return_client_map(
-    mut events : EventReader<ServerEvent>,
-    mut mapped : ResMut<ClientEntityMap>,
-    cached     : Res<CachedClientMap>
+    mut events      : EventReader<ServerEvent>,
+    mut mapped      : ResMut<ClientEntityMap>,
+    mut records     : ResMut<ClientSessionRecords>,
+    mut reconnected : EventWriter<ClientReconnected>,
+    cached          : Res<CachedClientMap>
 ){
     for event in events.read()
     {
-        match event
+        let ServerEvent::ClientConnected{ client_id } = event else { continue; };
+
+        let mut restored_mappings = 0;
+        if let Some(mappings) = cached.clients.get(client_id)
         {
-            ServerEvent::ClientConnected{ client_id } =>
+            for (server_entity, mapping) in mappings.iter()
             {
-                for (server_entity, (mapped_client_id, client_entity)) in cached.iter()
-                {
-                    if client_id != mapped_client_id { continue; }
-                    mapped.insert(*client_id, ClientMapping{ server_entity: *server_entity, client_entity: *client_entity });
-                }
+                let client_entity = mapping.client_entity;
+                mapped.insert(*client_id, ClientMapping{ server_entity: *server_entity, client_entity });
             }
-            _ => (),
+            restored_mappings = mappings.len();
         }
+
+        let record = records.entry(*client_id).or_default();
+        let Some((disconnected_at, _)) = record.disconnected.take() else { continue; };
+        let offline_duration = disconnected_at.elapsed();
+        tracing::debug!(?client_id, restored_mappings, ?offline_duration, "client reconnected");
+        reconnected.send(ClientReconnected{ client_id: *client_id, restored_mappings, offline_duration });
     }
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-fn clean_client_map(mut cached: ResMut<CachedClientMap>, mut despawns: RemovedComponents<Replicated>)
-{
-    for server_entity in despawns.read()
+/// Sends the server's session epoch and replication fingerprint to newly connected clients.
+fn send_session_info(
+    mut events  : EventReader<ServerEvent>,
+    mut server  : ResMut<RepliconServer>,
+    channel     : Res<RepairSessionChannel>,
+    epoch       : Res<ServerSessionEpoch>,
+    fingerprint : Res<ReplicationFingerprint>,
+){
+    for event in events.read()
+    {
+        let ServerEvent::ClientConnected{ client_id } = event else { continue; };
+        let info = SessionInfo{ epoch: epoch.0, fingerprint: fingerprint.0 };
+        let message = bincode::serialize(&info).expect("session info should serialize");
+        server.send(*client_id, channel.server, message);
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Compares clients' replication fingerprints with the server's.
+fn receive_client_session_info(
+    mut server     : ResMut<RepliconServer>,
+    mut mismatches : EventWriter<ClientProtocolMismatch>,
+    channel        : Res<RepairSessionChannel>,
+    fingerprint    : Res<ReplicationFingerprint>,
+){
+    for (client_id, message) in server.receive(channel.client)
     {
-        if let Some((client_id, client_entity)) = cached.remove(&server_entity)
+        let Ok(info) = bincode::deserialize::<ClientSessionInfo>(&message) else { continue; };
+        if info.fingerprint == fingerprint.0 { continue; }
+
+        tracing::error!(?client_id, server_fingerprint = fingerprint.0, client_fingerprint = info.fingerprint,
+            "replication protocol mismatch with client");
+        mismatches.send(ClientProtocolMismatch{
+            client_id,
+            server_fingerprint: fingerprint.0,
+            client_fingerprint: info.fingerprint,
+        });
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Removes cached mappings of despawned server entities.
+///
+/// Server entities that lose [`Replicated`] without being despawned keep their mappings, in case `Replicated` is
+/// re-added later.
+fn clean_client_map(
+    mut cached   : ResMut<CachedClientMap>,
+    mut pending  : ResMut<PendingMappingEvictions>,
+    mut removals : RemovedComponents<Replicated>,
+    entities     : Query<Has<Replicated>>,
+){
+    pending.extend(removals.read());
+    if pending.is_empty() { return; }
+
+    let mut despawned = Vec::new();
+    pending.retain(
+        |server_entity|
         {
+            match entities.get(*server_entity)
+            {
+                // Replicated was re-added
+                Ok(true)  => false,
+                // wait until the entity is despawned or Replicated is re-added
+                Ok(false) => true,
+                Err(_) =>
+                {
+                    despawned.push(*server_entity);
+                    false
+                }
+            }
+        }
+    );
+
+    for server_entity in despawned
+    {
+        for (client_id, mappings) in cached.clients.iter_mut()
+        {
+            let Some(CachedMapping{ client_entity, .. }) = mappings.remove(&server_entity) else { continue; };
             tracing::trace!(?client_id, ?server_entity, ?client_entity,
                 "removing despawned server entity from cached client-entity map");
         }
     }
+
+    cached.clients.retain(|_, mappings| !mappings.is_empty());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Records when clients disconnect so their cached mappings can expire.
+fn track_disconnected_clients(
+    mut events  : EventReader<ServerEvent>,
+    mut records : ResMut<ClientSessionRecords>,
+    tick        : Res<ServerTick>,
+){
+    for event in events.read()
+    {
+        let ServerEvent::ClientDisconnected{ client_id, .. } = event else { continue; };
+        records.entry(*client_id).or_default().disconnected = Some((Instant::now(), **tick));
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Discards cached mappings of clients that have been disconnected for longer than [`ServerPlugin::mapping_expiry`].
+fn expire_client_mappings(
+    mut cached       : ResMut<CachedClientMap>,
+    mut records      : ResMut<ClientSessionRecords>,
+    mut identities   : Option<ResMut<ClientIdentities>>,
+    mut events       : EventWriter<ClientMappingsExpired>,
+    config           : Res<CachedMappingConfig>,
+    tick             : Res<ServerTick>,
+){
+    records.retain(
+        |client_id, record|
+        {
+            let Some((disconnected_at, disconnect_tick)) = record.disconnected else { return true; };
+            let expired = match config.expiry
+            {
+                MappingExpiry::Never              => false,
+                MappingExpiry::Duration(duration) => disconnected_at.elapsed() >= duration,
+                MappingExpiry::Ticks(ticks)       => **tick - disconnect_tick >= ticks,
+            };
+            if !expired { return true; }
+
+            if let Some(identities) = &mut identities { identities.remove(client_id); }
+            let count = cached.remove_client(*client_id);
+            if count > 0
+            {
+                tracing::debug!(?client_id, count, "discarding expired cached client mappings");
+                events.send(ClientMappingsExpired{ client_id: *client_id, count });
+            }
+
+            false
+        }
+    );
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Forgets the oldest disconnected clients once there are more than [`ServerPlugin::max_session_records`], along
+/// with their cached mappings.
+fn enforce_max_session_records(
+    mut cached     : ResMut<CachedClientMap>,
+    mut records    : ResMut<ClientSessionRecords>,
+    mut identities : Option<ResMut<ClientIdentities>>,
+    mut events     : EventWriter<ClientMappingsExpired>,
+    config         : Res<CachedMappingConfig>,
+){
+    let Some(max) = config.max_records else { return; };
+
+    let mut disconnected: Vec<(Instant, ClientId)> = records
+        .iter()
+        .filter_map(|(client_id, record)| record.disconnected.map(|(disconnected_at, _)| (disconnected_at, *client_id)))
+        .collect();
+    if disconnected.len() <= max { return; }
+
+    disconnected.sort_unstable_by_key(|(disconnected_at, _)| *disconnected_at);
+    let excess = disconnected.len() - max;
+    for (_, client_id) in disconnected.into_iter().take(excess)
+    {
+        records.remove(&client_id);
+        if let Some(identities) = &mut identities { identities.remove(&client_id); }
+        let count = cached.remove_client(client_id);
+        tracing::debug!(?client_id, count, "forgetting disconnected client over the session record limit");
+        if count > 0
+        {
+            events.send(ClientMappingsExpired{ client_id, count });
+        }
+    }
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 //-------------------------------------------------------------------------------------------------------------------
 
-/// System set in [`PostUpdate`] for server repair. Runs before [`ServerSet::Send`].
-#[derive(SystemSet, Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
-pub struct ServerRepairSet;
+/// Discards the oldest cached mappings of clients with more than [`ServerPlugin::max_cached_mappings`].
+fn enforce_max_cached_mappings(
+    mut cached : ResMut<CachedClientMap>,
+    mut events : EventWriter<ClientMappingsExpired>,
+    config     : Res<CachedMappingConfig>,
+){
+    let Some(max) = config.max_per_client else { return; };
+
+    for (client_id, mappings) in cached.clients.iter_mut()
+    {
+        let count = mappings.len();
+        if count <= max { continue; }
+
+        if max == 0
+        {
+            mappings.clear();
+        }
+        else
+        {
+            let mut sequences: Vec<u64> = mappings.values().map(|mapping| mapping.sequence).collect();
+            sequences.sort_unstable();
+            let cutoff = sequences[count - max];
+            mappings.retain(|_, mapping| mapping.sequence >= cutoff);
+        }
+
+        tracing::debug!(?client_id, count = count - max, "discarding oldest cached client mappings over the limit");
+        events.send(ClientMappingsExpired{ client_id: *client_id, count: count - max });
+    }
+
+    cached.clients.retain(|_, mappings| !mappings.is_empty());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// System sets for server repair.
+///
+/// In [`PostUpdate`], the sets run in the order they are declared, after [`ServerSet::StoreHierarchy`] and before
+/// [`ServerSet::Send`]. [`ServerRepairSet::Collect`] also runs in [`PreUpdate`] between [`ServerSet::ReceivePackets`]
+/// and [`ServerSet::Receive`].
+#[derive(SystemSet, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum ServerRepairSet
+{
+    /// Caches client entity mappings from `ClientEntityMap`.
+    Collect,
+    /// Removes cached mappings for despawned server entities, and expired mappings.
+    Clean,
+    /// Returns cached mappings to `ClientEntityMap`, sends [`ClientReconnected`], and sends [`ServerSessionEpoch`] when
+    /// clients connect.
+    Restore,
+}
 
 /// Adds client repair functionality to a server app that uses `bevy_replicon`.
+/// - Sends [`ServerSessionEpoch`] to connecting clients and checks their [`replication_fingerprint`] if
+///   [`AppReplicationRepairExt::add_repair_session_channel`] was called. See [`ClientProtocolMismatch`].
 /// - Preserves client entity mappings for disconnected clients.
 ///   This is most useful for repairing entity mappings when a client prespawn notification is applied on the server
 ///   but then the client disconnects before it can receive the replicated server entity.
 ///   Since the client won't have the mapping, we need to link the server entity to the client entity after the client
 ///   reconnects so the client doesn't end up with a dangling prespawned entity.
+///   See [`Self::mapping_expiry`] and [`Self::max_cached_mappings`] for limiting how many mappings are kept.
+/// - Sends [`ClientReconnected`] when a previously connected client reconnects.
+/// - Returns cached mappings to reconnecting clients with new `ClientId`s if
+///   [`AppReplicationRepairExt::set_client_identity_resolver`] was called.
+///
+/// Each client can have its own mapping for a server entity, e.g. if multiple clients prespawned the same entity.
 ///
-/// Note that if [`Replicated`] is removed from a mapped server entity and reinserted, then the mapping will not be
-/// sent in the next reconnect.
-/// This may be a source of bugs, so be careful.
+/// Cached mappings are discarded when their server entities are despawned. If [`Replicated`] is removed from a mapped
+/// server entity without despawning it, then its mappings are kept in case `Replicated` is reinserted.
 ///
 /// This plugin must be added after `bevy_replicon`'s `ClientPlugin`.
 #[derive(Debug)]
-pub struct ServerPlugin;
+pub struct ServerPlugin
+{
+    /// Controls when cached client entity mappings of disconnected clients are discarded.
+    /// [`ClientMappingsExpired`] is sent when mappings are discarded.
+    ///
+    /// Defaults to [`MappingExpiry::Never`].
+    pub mapping_expiry: MappingExpiry,
+    /// The max number of cached client entity mappings per client. When a client has more than this many, its oldest
+    /// mappings are discarded and [`ClientMappingsExpired`] is sent.
+    ///
+    /// `Some(0)` discards all mappings, so nothing is returned to reconnecting clients.
+    ///
+    /// Defaults to `None`, which allows unlimited mappings.
+    pub max_cached_mappings: Option<usize>,
+    /// The max number of disconnected clients to remember. When there are more than this many, the clients that
+    /// disconnected first are forgotten along with their cached mappings, so they won't be reported as
+    /// [`ClientReconnected`] when they come back.
+    ///
+    /// Disconnected clients are also forgotten when their mappings expire (see [`Self::mapping_expiry`]).
+    ///
+    /// Defaults to `Some(1024)`.
+    pub max_session_records: Option<usize>,
+}
+
+impl Default for ServerPlugin
+{
+    fn default() -> Self
+    {
+        Self{
+            mapping_expiry: MappingExpiry::default(),
+            max_cached_mappings: None,
+            max_session_records: Some(1024),
+        }
+    }
+}
 
 impl Plugin for ServerPlugin
 {
@@ -106,39 +632,79 @@ impl Plugin for ServerPlugin
         if !app.world().contains_resource::<ComponentRepairRules>()
         { app.world_mut().init_resource::<ComponentRepairRules>(); }
 
+        if !app.world().contains_resource::<ServerSessionEpoch>()
+        { app.init_resource::<ServerSessionEpoch>(); }
+
         app.init_resource::<CachedClientMap>()
+            .init_resource::<PendingMappingEvictions>()
+            .init_resource::<ClientSessionRecords>()
+            .insert_resource(CachedMappingConfig{
+                expiry: self.mapping_expiry,
+                max_per_client: self.max_cached_mappings,
+                max_records: self.max_session_records,
+            })
+            .add_event::<ClientProtocolMismatch>()
+            .add_event::<ClientMappingsExpired>()
+            .add_event::<ClientReconnected>()
+            .add_systems(PreStartup, init_replication_fingerprint)
             .configure_sets(PreUpdate,
-                ServerRepairSet
+                ServerRepairSet::Collect
                     .after(ServerSet::ReceivePackets)
                     .before(ServerSet::Receive)
                     .run_if(resource_exists::<ServerTick>)
             )
             .configure_sets(PostUpdate,
-                ServerRepairSet
+                (
+                    ServerRepairSet::Collect,
+                    ServerRepairSet::Clean,
+                    ServerRepairSet::Restore,
+                )
+                    .chain()
                     .after(ServerSet::StoreHierarchy)
                     .before(ServerSet::Send)
                     .run_if(resource_exists::<ServerTick>)
             )
             .add_systems(PreUpdate,
-                (
-                    // collect the current map before it gets cleaned up due to a disconnect
-                    // - This is mainly needed for unit tests where mappings are inserted manually.
-                    collect_client_map,
-                )
-                    .in_set(ServerRepairSet)
+                // collect the current map before it gets cleaned up due to a disconnect
+                // - This is mainly needed for unit tests where mappings are inserted manually.
+                collect_client_map
+                    .in_set(ServerRepairSet::Collect)
+            )
+            .add_systems(PreUpdate,
+                receive_client_session_info
+                    .after(ServerSet::ReceivePackets)
+                    .before(ServerSet::Receive)
+                    .run_if(resource_exists::<RepairSessionChannel>)
+                    .run_if(resource_exists::<ServerTick>)
             )
             .add_systems(PostUpdate,
                 (
                     // collect the current map
-                    collect_client_map,
+                    (
+                        collect_client_map,
+                        track_disconnected_clients,
+                    )
+                        .in_set(ServerRepairSet::Collect),
                     // clean immediately before repairing the client map to avoid missing despawns
-                    // - We assume the server does not remove and re-add Replicated to client-mapped server entities.
-                    clean_client_map,
+                    (
+                        clean_client_map,
+                        expire_client_mappings,
+                        enforce_max_cached_mappings,
+                        enforce_max_session_records,
+                    )
+                        .chain()
+                        .in_set(ServerRepairSet::Clean),
                     // return existing client mappings as soon as a client connection is detected
-                    return_client_map,
+                    (
+                        rebind_client_identities.run_if(resource_exists::<ClientIdentityResolver>),
+                        return_client_map,
+                    )
+                        .chain()
+                        .in_set(ServerRepairSet::Restore),
+                    send_session_info
+                        .run_if(resource_exists::<RepairSessionChannel>)
+                        .in_set(ServerRepairSet::Restore),
                 )
-                    .chain()
-                    .in_set(ServerRepairSet)
             );
     }
 }
@@ -0,0 +1,166 @@
+//local shortcuts
+
+//third-party shortcuts
+use bevy::prelude::*;
+use bevy_replicon::core::channels::{ChannelKind, RepliconChannels};
+use bevy_replicon::core::replication::replication_rules::ReplicationRules;
+use serde::{Deserialize, Serialize};
+
+//standard shortcuts
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Channels for session messages. Inserted by
+/// [`AppReplicationRepairExt::add_repair_session_channel`](crate::AppReplicationRepairExt::add_repair_session_channel).
+#[derive(Resource, Debug, Copy, Clone)]
+pub(crate) struct RepairSessionChannel
+{
+    /// Server -> client channel.
+    pub(crate) server: u8,
+    /// Client -> server channel.
+    pub(crate) client: u8,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Message sent by the server to each client when it connects.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct SessionInfo
+{
+    pub(crate) epoch: u64,
+    pub(crate) fingerprint: u64,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Message sent by each client to the server when it connects.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct ClientSessionInfo
+{
+    pub(crate) fingerprint: u64,
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+pub(crate) fn register_session_channel(app: &mut App)
+{
+    if app.world().contains_resource::<RepairSessionChannel>() { return; }
+
+    let mut channels = app.world_mut().resource_mut::<RepliconChannels>();
+    let server = channels.create_server_channel(ChannelKind::Ordered.into());
+    let client = channels.create_client_channel(ChannelKind::Ordered.into());
+    app.insert_resource(RepairSessionChannel{ server, client });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// FNV-1a, which is stable across processes and platforms.
+struct Fingerprinter(u64);
+
+impl Fingerprinter
+{
+    fn new() -> Self
+    {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8])
+    {
+        for byte in bytes
+        {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_str(&mut self, value: &str)
+    {
+        self.write(&(value.len() as u64).to_le_bytes());
+        self.write(value.as_bytes());
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Computes a fingerprint of the app's replication protocol.
+///
+/// The fingerprint covers `bevy_replicon`'s replication rules (component type names and rule priorities, in rule
+/// order). Apps with matching fingerprints registered the same replicated components in the same order.
+///
+/// Repair rules in [`ComponentRepairRules`](crate::ComponentRepairRules) are not included since they only affect
+/// the client, so a client can add repair policies with
+/// [`add_replication_repair_policy`](crate::AppReplicationRepairExt::add_replication_repair_policy) that the server
+/// doesn't have.
+///
+/// If [`add_repair_session_channel`](crate::AppReplicationRepairExt::add_repair_session_channel) was called, the
+/// server and client exchange fingerprints when a client connects. See
+/// [`ProtocolMismatch`](crate::ProtocolMismatch).
+pub fn replication_fingerprint(world: &World) -> u64
+{
+    let mut fingerprint = Fingerprinter::new();
+    let component_name = |id| world.components().get_info(id).map(|info| info.name()).unwrap_or_default();
+
+    if let Some(rules) = world.get_resource::<ReplicationRules>()
+    {
+        for rule in rules.iter()
+        {
+            fingerprint.write(&(rule.priority as u64).to_le_bytes());
+            fingerprint.write(&(rule.components.len() as u64).to_le_bytes());
+            for (component_id, _) in rule.components.iter()
+            {
+                fingerprint.write_str(component_name(*component_id));
+            }
+        }
+    }
+
+    fingerprint.0
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// Resource with the server's session epoch.
+///
+/// The epoch identifies a server process. [`ServerPlugin`](crate::ServerPlugin) inserts a new epoch when it is
+/// built, unless this resource was already inserted. If you persist server state across restarts such that client
+/// entities can still be repaired, you can insert a persisted epoch before adding the plugin.
+///
+/// The epoch is only sent to clients if
+/// [`add_repair_session_channel`](crate::AppReplicationRepairExt::add_repair_session_channel) was called.
+#[derive(Resource, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ServerSessionEpoch(pub u64);
+
+impl ServerSessionEpoch
+{
+    /// Makes an epoch that is unique to the current process (with high probability).
+    pub fn new() -> Self
+    {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default();
+        Self(nanos ^ ((std::process::id() as u64) << 32))
+    }
+}
+
+impl Default for ServerSessionEpoch
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+//-------------------------------------------------------------------------------------------------------------------
+
+/// The app's [`replication_fingerprint`], computed at startup after all replication rules are registered.
+#[derive(Resource, Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct ReplicationFingerprint(pub(crate) u64);
+
+//-------------------------------------------------------------------------------------------------------------------
+
+pub(crate) fn init_replication_fingerprint(world: &mut World)
+{
+    let fingerprint = replication_fingerprint(world);
+    world.insert_resource(ReplicationFingerprint(fingerprint));
+}
+
+//-------------------------------------------------------------------------------------------------------------------
@@ -33,6 +33,20 @@ pub(super) fn disconnect(server_app: &mut App, client_app: &mut App)
 
 //-------------------------------------------------------------------------------------------------------------------
 
+#[allow(dead_code)]
+pub(super) fn fail_connection_attempt(client_app: &mut App)
+{
+    let mut client = client_app.world_mut().resource_mut::<RepliconClient>();
+    assert!(client.is_disconnected(), "client must be disconnected before a connection attempt");
+
+    client.set_status(RepliconClientStatus::Connecting);
+    client_app.update();
+    client_app.world_mut().resource_mut::<RepliconClient>().set_status(RepliconClientStatus::Disconnected);
+    client_app.update();
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
 pub(super) fn reconnect(server_app: &mut App, client_app: &mut App, client_id: ClientId)
 {
     let mut client = client_app.world_mut().resource_mut::<RepliconClient>();
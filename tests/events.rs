@@ -0,0 +1,428 @@
+//modules
+mod common;
+
+//local shortcuts
+use bevy_replicon_repair::*;
+use common::{BasicComponent, DummyComponent};
+
+//third-party shortcuts
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon::test_app::ServerTestAppExt;
+
+//standard shortcuts
+
+
+//-------------------------------------------------------------------------------------------------------------------
+
+fn current_events<E: Event + Copy>(app: &App) -> Vec<E>
+{
+    app.world().resource::<Events<E>>().iter_current_update_events().copied().collect()
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// lifecycle events are sent for each repair state transition
+#[test]
+fn lifecycle_events()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    server_app.world_mut().spawn((Replicated, BasicComponent::default()));
+    server_app.world_mut().spawn((Replicated, BasicComponent::default(), DummyComponent));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(client_app.world().entities().len(), 2);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    let disconnected = current_events::<Disconnected>(&client_app);
+    assert_eq!(disconnected.len(), 1);
+
+    // despawn one entity and remove a component from the other
+    let despawned = server_app
+        .world_mut()
+        .query_filtered::<Entity, (With<Replicated>, Without<DummyComponent>)>()
+        .single(server_app.world());
+    server_app.world_mut().despawn(despawned);
+    let mutated = server_app
+        .world_mut()
+        .query_filtered::<Entity, (With<Replicated>, With<DummyComponent>)>()
+        .single(server_app.world());
+    server_app.world_mut().entity_mut(mutated).remove::<DummyComponent>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    let waiting = current_events::<ReconnectWaiting>(&client_app);
+    assert_eq!(waiting, vec![ReconnectWaiting{ tick: disconnected[0].tick }]);
+    assert!(current_events::<RepairStarted>(&client_app).is_empty());
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    let started = current_events::<RepairStarted>(&client_app);
+    let finished = current_events::<RepairFinished>(&client_app);
+    assert_eq!(started.len(), 1);
+    assert!(started[0].tick > disconnected[0].tick);
+    assert_eq!(finished, vec![RepairFinished{
+        tick: started[0].tick,
+        counts: RepairCounts{
+            despawned_entities: 1,
+            despawned_prespawns: 0,
+            repaired_entities: 1,
+            removed_components: 1,
+        },
+    }]);
+    assert_eq!(client_app.world().entities().len(), 1);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// removed components are counted even if components are inserted during repair
+#[test]
+fn repair_counts_with_inserts()
+{
+    #[derive(Component)]
+    struct RemovalMarker;
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair_policy(RepairPolicy::<DummyComponent>::Custom(
+            |entity, preinit_tick|
+            {
+                if component_was_replicated::<DummyComponent>(entity, preinit_tick) { return; }
+                entity.remove::<DummyComponent>().insert(RemovalMarker);
+            }
+        ));
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default(), DummyComponent)).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // remove a component
+    server_app.world_mut().entity_mut(server_entity).remove::<DummyComponent>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    let finished = current_events::<RepairFinished>(&client_app);
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].counts.removed_components, 1);
+    assert_eq!(client_app.world_mut().query::<&RemovalMarker>().iter(client_app.world()).count(), 1);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// despawn and component-removal triggers fire before the data is gone
+#[test]
+fn repair_notifications()
+{
+    #[derive(Resource, Default)]
+    struct Notifications
+    {
+        despawned: Vec<(Entity, Option<Entity>, bool)>,
+        removed: Vec<(Entity, BasicComponent)>,
+    }
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<Notifications>()
+        .add_observer(
+            |trigger: Trigger<RepairDespawned>, mut n: ResMut<Notifications>, q: Query<(), With<BasicComponent>>|
+            {
+                let event = trigger.event();
+                n.despawned.push((event.entity, event.server_entity, q.contains(trigger.entity())));
+            }
+        )
+        .add_observer(
+            |mut trigger: Trigger<RepairRemoved<BasicComponent>>, mut n: ResMut<Notifications>|
+            {
+                let entity = trigger.entity;
+                let value = std::mem::take(&mut trigger.event_mut().value);
+                n.removed.push((entity, value));
+            }
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_despawned = server_app.world_mut().spawn((Replicated, BasicComponent(1))).id();
+    let server_mutated = server_app.world_mut().spawn((Replicated, BasicComponent(2), DummyComponent)).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_despawned = *entity_map.to_client().get(&server_despawned).unwrap();
+    let client_mutated = *entity_map.to_client().get(&server_mutated).unwrap();
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    server_app.world_mut().despawn(server_despawned);
+    server_app.world_mut().entity_mut(server_mutated).remove::<BasicComponent>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    let notifications = client_app.world().resource::<Notifications>();
+    assert_eq!(notifications.despawned, vec![(client_despawned, Some(server_despawned), true)]);
+    assert_eq!(notifications.removed, vec![(client_mutated, BasicComponent(2))]);
+    assert!(client_app.world().get_entity(client_despawned).is_err());
+    assert!(!client_app.world().entity(client_mutated).contains::<BasicComponent>());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// repair state is mirrored into bevy states and run conditions track it
+#[test]
+fn repair_states()
+{
+    #[derive(Resource, Default, Debug, Eq, PartialEq)]
+    struct Tracked
+    {
+        entered_disconnected: usize,
+        entered_done: usize,
+        just_finished: usize,
+        awaiting: usize,
+    }
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<Tracked>()
+        .add_systems(OnEnter(ClientRepairState::Disconnected), |mut t: ResMut<Tracked>| t.entered_disconnected += 1)
+        .add_systems(OnEnter(ClientRepairState::Done), |mut t: ResMut<Tracked>| t.entered_done += 1)
+        .add_systems(Update, (|mut t: ResMut<Tracked>| t.just_finished += 1).run_if(repair_just_finished))
+        .add_systems(Update, (|mut t: ResMut<Tracked>| t.awaiting += 1).run_if(awaiting_reconnect));
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    server_app.world_mut().spawn((Replicated, BasicComponent::default()));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(**client_app.world().resource::<State<ClientRepairState>>(), ClientRepairState::Dormant);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    assert_eq!(**client_app.world().resource::<State<ClientRepairState>>(), ClientRepairState::Disconnected);
+    assert_eq!(client_app.world().resource::<Tracked>().entered_disconnected, 1);
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    assert_eq!(**client_app.world().resource::<State<ClientRepairState>>(), ClientRepairState::Waiting);
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(**client_app.world().resource::<State<ClientRepairState>>(), ClientRepairState::Done);
+
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<Tracked>(), Tracked{
+        entered_disconnected: 1,
+        entered_done: 1,
+        just_finished: 1,
+        awaiting: 2,
+    });
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// the server sends reconnect events for returning clients only
+#[test]
+fn server_reconnect_events()
+{
+    #[derive(Resource, Default)]
+    struct Reconnected(Vec<ClientReconnected>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    let mut new_client_app = App::new();
+    for app in [&mut server_app, &mut client_app, &mut new_client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app
+        .add_plugins(bevy_replicon_repair::ServerPlugin::default())
+        .init_resource::<Reconnected>()
+        .add_systems(Update,
+            |mut events: EventReader<ClientReconnected>, mut reconnected: ResMut<Reconnected>|
+            {
+                reconnected.0.extend(events.read().copied());
+            }
+        );
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    server_app.update();
+    assert!(server_app.world().resource::<Reconnected>().0.is_empty());
+
+    let client_entity = client_app.world_mut().spawn(Prespawned).id();
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default())).id();
+    server_app.world_mut().resource_mut::<ClientEntityMap>().insert(client_id, ClientMapping{ server_entity, client_entity });
+    server_app.update();
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    let reconnected = &server_app.world().resource::<Reconnected>().0;
+    assert_eq!(reconnected.len(), 1);
+    assert_eq!(reconnected[0].client_id, client_id);
+    assert_eq!(reconnected[0].restored_mappings, 1);
+
+    // a new client is not reconnecting
+    let new_client_id = common::connect(&mut server_app, &mut new_client_app);
+    server_app.update();
+    assert_eq!(server_app.world().resource::<Reconnected>().0.len(), 1);
+
+    // a client with no cached mappings is still reconnecting
+    common::disconnect(&mut server_app, &mut new_client_app);
+    server_app.update();
+    common::reconnect(&mut server_app, &mut new_client_app, new_client_id);
+    server_app.update();
+    let reconnected = &server_app.world().resource::<Reconnected>().0;
+    assert_eq!(reconnected.len(), 2);
+    assert_eq!(reconnected[1].client_id, new_client_id);
+    assert_eq!(reconnected[1].restored_mappings, 0);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// the server forgets the clients that disconnected first when there are too many disconnected clients
+#[test]
+fn server_session_record_limit()
+{
+    #[derive(Resource, Default)]
+    struct Reconnected(Vec<ClientReconnected>);
+
+    let mut server_app = App::new();
+    let mut client_app1 = App::new();
+    let mut client_app2 = App::new();
+    for app in [&mut server_app, &mut client_app1, &mut client_app2] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app
+        .add_plugins(bevy_replicon_repair::ServerPlugin{ max_session_records: Some(1), ..Default::default() })
+        .init_resource::<Reconnected>()
+        .add_systems(Update,
+            |mut events: EventReader<ClientReconnected>, mut reconnected: ResMut<Reconnected>|
+            {
+                reconnected.0.extend(events.read().copied());
+            }
+        );
+
+    // initial connections
+    let client_id1 = common::connect(&mut server_app, &mut client_app1);
+    let client_id2 = common::connect(&mut server_app, &mut client_app2);
+    server_app.update();
+
+    // disconnect both clients, the first client is forgotten
+    common::disconnect(&mut server_app, &mut client_app1);
+    server_app.update();
+    common::disconnect(&mut server_app, &mut client_app2);
+    server_app.update();
+
+    // reconnect: only the second client is reconnecting
+    common::reconnect(&mut server_app, &mut client_app1, client_id1);
+    common::reconnect(&mut server_app, &mut client_app2, client_id2);
+    server_app.update();
+    let reconnected = &server_app.world().resource::<Reconnected>().0;
+    assert_eq!(reconnected.len(), 1);
+    assert_eq!(reconnected[0].client_id, client_id2);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
@@ -31,8 +31,8 @@ fn prespawn_normal()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    server_app.add_plugins(bevy_replicon_repair::ServerPlugin);
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true });
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
 
     let client_id = common::connect(&mut server_app, &mut client_app);
 
@@ -47,7 +47,7 @@ fn prespawn_normal()
     let replicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Prespawned>, With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
     assert_eq!(replicated_client_entity, client_entity);
 }
@@ -70,8 +70,8 @@ fn prespawn_replicated_and_survives()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    server_app.add_plugins(bevy_replicon_repair::ServerPlugin);
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true });
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -87,7 +87,7 @@ fn prespawn_replicated_and_survives()
     let replicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Prespawned>, With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
     assert_eq!(replicated_client_entity, client_entity);
 
@@ -108,7 +108,7 @@ fn prespawn_replicated_and_survives()
     let replicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Prespawned>, With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
     assert_eq!(replicated_client_entity, client_entity);
 }
@@ -139,8 +139,8 @@ fn prespawn_not_replicated_and_survives()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    server_app.add_plugins(bevy_replicon_repair::ServerPlugin);
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true });
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -168,7 +168,7 @@ fn prespawn_not_replicated_and_survives()
         .world_mut()
         //.query_filtered::<Entity, (With<Prespawned>, With<Replicated>, With<BasicComponent>)>()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
     assert_eq!(replicated_client_entity, client_entity);
 }
@@ -191,8 +191,8 @@ fn prespawn_at_disconnect_survives()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    server_app.add_plugins(bevy_replicon_repair::ServerPlugin);
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true });
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -218,7 +218,7 @@ fn prespawn_at_disconnect_survives()
     let replicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Prespawned>, With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
     assert_eq!(replicated_client_entity, client_entity);
 }
@@ -250,8 +250,8 @@ fn prespawn_fail_dies_with_cleanup()
         .replicate_repair::<BasicComponent>()
         .replicate_repair::<DummyComponent>();
     }
-    server_app.add_plugins(bevy_replicon_repair::ServerPlugin);
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true });
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -276,13 +276,148 @@ fn prespawn_fail_dies_with_cleanup()
     let replicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (Without<Prespawned>, With<Replicated>, With<DummyComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
     assert_ne!(replicated_client_entity, client_entity);
 }
 
 //-------------------------------------------------------------------------------------------------------------------
 
+// replicated prespawned entity despawned on server during disconnect is deferred like other replicated entities
+#[test]
+fn prespawn_despawned_deferred()
+{
+    #[derive(Resource, Default)]
+    struct Notifications
+    {
+        despawned: Vec<Entity>,
+        finished: Vec<RepairFinished>,
+    }
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{
+            cleanup_prespawns: true,
+            despawn_mode: RepairDespawnMode::Deferred(std::time::Duration::from_secs(3600)),
+            ..Default::default()
+        })
+        .init_resource::<Notifications>()
+        .add_observer(
+            |trigger: Trigger<RepairDespawned>, mut n: ResMut<Notifications>|
+            {
+                n.despawned.push(trigger.event().entity);
+            }
+        )
+        .add_systems(Update,
+            |mut events: EventReader<RepairFinished>, mut n: ResMut<Notifications>|
+            {
+                n.finished.extend(events.read().copied());
+            }
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let client_entity = client_app.world_mut().spawn(Prespawned).id();
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+    server_app.world_mut().resource_mut::<ClientEntityMap>().insert(client_id, ClientMapping{ server_entity, client_entity });
+    server_app.world_mut().spawn((Replicated, BasicComponent(1)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert!(client_app.world().entity(client_entity).contains::<Replicated>());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // despawn entity
+    server_app.world_mut().despawn(server_entity);
+    server_app.update();
+
+    // reconnect: the entity is waiting to be despawned
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert!(client_app.world().entity(client_entity).contains::<RepairDespawning>());
+    let notifications = client_app.world().resource::<Notifications>();
+    assert_eq!(notifications.despawned, vec![client_entity]);
+    assert_eq!(notifications.finished.len(), 1);
+    assert_eq!(notifications.finished[0].counts.despawned_entities, 1);
+    assert_eq!(notifications.finished[0].counts.despawned_prespawns, 0);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// replicated prespawned entity despawned on server during disconnect survives reconnect with never_despawn
+#[test]
+fn prespawn_never_despawn_survives()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let client_entity = client_app.world_mut().spawn(Prespawned).id();
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+    server_app.world_mut().resource_mut::<ClientEntityMap>().insert(client_id, ClientMapping{ server_entity, client_entity });
+    server_app.world_mut().spawn((Replicated, BasicComponent(1)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    client_app.world_mut().entity_mut(client_entity).insert(RepairOverride{ never_despawn: true, ..Default::default() });
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // despawn entity
+    server_app.world_mut().despawn(server_entity);
+    server_app.update();
+
+    // reconnect: the entity becomes a normal client entity
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    let entity = client_app.world().entity(client_entity);
+    assert!(!entity.contains::<Replicated>());
+    assert!(!entity.contains::<Prespawned>());
+    assert!(entity.contains::<BasicComponent>());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
 // prespawned entity not spawned on server during disconnect survives reconnect if cleanup option is set to false
 #[test]
 fn prespawn_fail_ignored_without_cleanup()
@@ -300,8 +435,8 @@ fn prespawn_fail_ignored_without_cleanup()
         .replicate_repair::<BasicComponent>()
         .replicate_repair::<DummyComponent>();
     }
-    server_app.add_plugins(bevy_replicon_repair::ServerPlugin);
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false });
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -326,11 +461,11 @@ fn prespawn_fail_ignored_without_cleanup()
     let unreplicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Prespawned>, Without<Replicated>, Without<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     let replicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (Without<Prespawned>, With<Replicated>, With<DummyComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 2);
     assert_eq!(unreplicated_client_entity, client_entity);
     assert_ne!(replicated_client_entity, client_entity);
@@ -363,8 +498,8 @@ fn prespawn_while_waiting_survives()
         .replicate_repair::<BasicComponent>()
         .replicate_repair::<DummyComponent>();
     }
-    server_app.add_plugins(bevy_replicon_repair::ServerPlugin);
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true });
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -396,9 +531,328 @@ fn prespawn_while_waiting_survives()
     let unreplicated_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Prespawned>, Without<Replicated>, Without<DummyComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 2);
     assert_eq!(unreplicated_client_entity, client_entity);
 }
 
 //-------------------------------------------------------------------------------------------------------------------
+
+// cached mappings are returned to a client that reconnects with a new client id but the same identity
+#[test]
+fn prespawn_rebound_to_new_client_id()
+{
+    // all clients log in to the same account
+    fn resolve_account(In(_): In<ClientId>) -> Option<ClientIdentity>
+    {
+        Some(ClientIdentity(42))
+    }
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app
+        .add_plugins(bevy_replicon_repair::ServerPlugin::default())
+        .set_client_identity_resolver(resolve_account);
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let client_entity = client_app.world_mut().spawn(Prespawned).id();
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default())).id();
+    server_app.world_mut().resource_mut::<ClientEntityMap>().insert(client_id, ClientMapping{ server_entity, client_entity });
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // reconnect with a new client id
+    let new_client_id = ClientId::new(client_id.get() + 1);
+    common::reconnect(&mut server_app, &mut client_app, new_client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let replicated_client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
+        .single(client_app.world());
+    assert_eq!(client_app.world().entities().len(), 1);
+    assert_eq!(replicated_client_entity, client_entity);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// cached mappings of clients that stay disconnected expire
+#[test]
+fn prespawn_mappings_expire()
+{
+    #[derive(Resource, Default)]
+    struct Expired(Vec<ClientMappingsExpired>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app
+        .add_plugins(bevy_replicon_repair::ServerPlugin{
+            mapping_expiry: MappingExpiry::Ticks(3),
+            max_cached_mappings: Some(2),
+            ..Default::default()
+        })
+        .init_resource::<Expired>()
+        .add_systems(Update,
+            |mut events: EventReader<ClientMappingsExpired>, mut expired: ResMut<Expired>|
+            {
+                expired.0.extend(events.read().copied());
+            }
+        );
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    // map more entities than the limit
+    for _ in 0..3
+    {
+        let client_entity = client_app.world_mut().spawn(Prespawned).id();
+        let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default())).id();
+        server_app
+            .world_mut()
+            .resource_mut::<ClientEntityMap>()
+            .insert(client_id, ClientMapping{ server_entity, client_entity });
+    }
+    server_app.update();
+    server_app.update();
+    assert_eq!(server_app.world().resource::<Expired>().0, vec![ClientMappingsExpired{ client_id, count: 1 }]);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.update();
+    assert_eq!(server_app.world().resource::<Expired>().0.len(), 1);
+
+    // mappings expire after enough ticks
+    server_app.update();
+    server_app.update();
+    server_app.update();
+    assert_eq!(server_app.world().resource::<Expired>().0, vec![
+        ClientMappingsExpired{ client_id, count: 1 },
+        ClientMappingsExpired{ client_id, count: 2 },
+    ]);
+
+    // reconnect: no mappings are returned, so server entities are replicated as new entities
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    let replicated = client_app.world_mut().query_filtered::<(), With<Replicated>>().iter(client_app.world()).count();
+    assert_eq!(replicated, 3);
+    assert_eq!(client_app.world().entities().len(), 6);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// no mappings are cached when the per-client limit is zero
+#[test]
+fn prespawn_mappings_zero_limit()
+{
+    #[derive(Resource, Default)]
+    struct Expired(Vec<ClientMappingsExpired>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app
+        .add_plugins(bevy_replicon_repair::ServerPlugin{
+            max_cached_mappings: Some(0),
+            ..Default::default()
+        })
+        .init_resource::<Expired>()
+        .add_systems(Update,
+            |mut events: EventReader<ClientMappingsExpired>, mut expired: ResMut<Expired>|
+            {
+                expired.0.extend(events.read().copied());
+            }
+        );
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    // map some entities
+    for _ in 0..2
+    {
+        let client_entity = client_app.world_mut().spawn(Prespawned).id();
+        let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default())).id();
+        server_app
+            .world_mut()
+            .resource_mut::<ClientEntityMap>()
+            .insert(client_id, ClientMapping{ server_entity, client_entity });
+    }
+    server_app.update();
+    server_app.update();
+    assert_eq!(server_app.world().resource::<Expired>().0, vec![ClientMappingsExpired{ client_id, count: 2 }]);
+
+    // reconnect: no mappings are returned, so server entities are replicated as new entities
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.update();
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    let replicated = client_app.world_mut().query_filtered::<(), With<Replicated>>().iter(client_app.world()).count();
+    assert_eq!(replicated, 2);
+    assert_eq!(client_app.world().entities().len(), 4);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// an entity prespawned by multiple clients keeps each client's mapping across reconnects
+#[test]
+fn shared_prespawn_survives()
+{
+    let mut server_app = App::new();
+    let mut client_app1 = App::new();
+    let mut client_app2 = App::new();
+    for app in [&mut server_app, &mut client_app1, &mut client_app2] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    for client_app in [&mut client_app1, &mut client_app2]
+    {
+        client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
+    }
+
+    // initial connection
+    let client_id1 = common::connect(&mut server_app, &mut client_app1);
+    let client_id2 = common::connect(&mut server_app, &mut client_app2);
+
+    // both clients prespawn the same entity
+    let client_entity1 = client_app1.world_mut().spawn(Prespawned).id();
+    let client_entity2 = client_app2.world_mut().spawn(Prespawned).id();
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default())).id();
+    let mut entity_map = server_app.world_mut().resource_mut::<ClientEntityMap>();
+    entity_map.insert(client_id1, ClientMapping{ server_entity, client_entity: client_entity1 });
+    entity_map.insert(client_id2, ClientMapping{ server_entity, client_entity: client_entity2 });
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app1);
+    common::disconnect(&mut server_app, &mut client_app2);
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app1, client_id1);
+    common::reconnect(&mut server_app, &mut client_app2, client_id2);
+    server_app.update();
+    for client_app in [&mut client_app1, &mut client_app2]
+    {
+        server_app.exchange_with_client(client_app);
+        client_app.update();
+        assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    }
+
+    // each client's prespawned entity was replicated
+    for (client_app, client_entity) in [(&mut client_app1, client_entity1), (&mut client_app2, client_entity2)]
+    {
+        let replicated_client_entity = client_app
+            .world_mut()
+            .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
+            .single(client_app.world());
+        assert_eq!(client_app.world().entities().len(), 1);
+        assert_eq!(replicated_client_entity, client_entity);
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// removing and reinserting Replicated on a mapped server entity keeps its cached mapping
+#[test]
+fn prespawn_survives_replicated_toggle()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let client_entity = client_app.world_mut().spawn(Prespawned).id();
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default())).id();
+    server_app.world_mut().resource_mut::<ClientEntityMap>().insert(client_id, ClientMapping{ server_entity, client_entity });
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // pause replication of the entity for a few ticks
+    server_app.world_mut().entity_mut(server_entity).remove::<Replicated>();
+    server_app.update();
+    server_app.update();
+    server_app.world_mut().entity_mut(server_entity).insert(Replicated);
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    let replicated_client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
+        .single(client_app.world());
+    assert_eq!(client_app.world().entities().len(), 1);
+    assert_eq!(replicated_client_entity, client_entity);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
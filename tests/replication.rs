@@ -9,6 +9,7 @@ use common::{BasicComponent, DummyComponent};
 use bevy::prelude::*;
 use bevy_replicon::prelude::*;
 use bevy_replicon::test_app::ServerTestAppExt;
+use serde::{Deserialize, Serialize};
 
 //standard shortcuts
 
@@ -31,7 +32,7 @@ fn normal_replication()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false });
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
 
     common::connect(&mut server_app, &mut client_app);
 
@@ -44,7 +45,7 @@ fn normal_replication()
     let _client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
 }
 
@@ -66,7 +67,7 @@ fn entity_persists()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false });
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -80,7 +81,7 @@ fn entity_persists()
     let initial_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
 
     // disconnect
@@ -96,7 +97,7 @@ fn entity_persists()
     let new_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(new_client_entity, initial_client_entity);
     assert_eq!(client_app.world().entities().len(), 1);
 }
@@ -119,7 +120,7 @@ fn disconnect_component_mutation_travels()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false });
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -133,7 +134,7 @@ fn disconnect_component_mutation_travels()
     let initial_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
 
     // disconnect
@@ -156,7 +157,7 @@ fn disconnect_component_mutation_travels()
     let (new_client_entity, component) = client_app
         .world_mut()
         .query_filtered::<(Entity, &BasicComponent), With<Replicated>>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(new_client_entity, initial_client_entity);
     assert_eq!(*component, BasicComponent(1));
     assert_eq!(client_app.world().entities().len(), 1);
@@ -180,7 +181,7 @@ fn disconnect_component_removal_travels()
         ))
         .replicate_repair::<BasicComponent>();
     }
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false });
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -194,7 +195,7 @@ fn disconnect_component_removal_travels()
     let initial_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
 
     // disconnect
@@ -204,7 +205,7 @@ fn disconnect_component_removal_travels()
     let server_entity = server_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&server_app.world());
+        .single(server_app.world());
     server_app.world_mut().entity_mut(server_entity).remove::<BasicComponent>();
 
     // reconnect
@@ -217,7 +218,7 @@ fn disconnect_component_removal_travels()
     let new_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, Without<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(new_client_entity, initial_client_entity);
     assert_eq!(client_app.world().entities().len(), 1);
 }
@@ -241,7 +242,7 @@ fn disconnect_despawn_travels()
         .replicate_repair::<BasicComponent>()
         .replicate_repair::<DummyComponent>();
     }
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false });
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -257,7 +258,7 @@ fn disconnect_despawn_travels()
     let initial_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 2);
 
     // disconnect
@@ -267,7 +268,7 @@ fn disconnect_despawn_travels()
     let server_entity = server_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&server_app.world());
+        .single(server_app.world());
     server_app.world_mut().despawn(server_entity);
 
     // reconnect
@@ -280,7 +281,7 @@ fn disconnect_despawn_travels()
     let dummy_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<DummyComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_ne!(dummy_client_entity, initial_client_entity);
     assert_eq!(client_app.world().entities().len(), 1);
 }
@@ -304,7 +305,7 @@ fn retained_component_not_removed()
         .replicate_repair::<BasicComponent>()
         .replicate_repair::<DummyComponent>();
     }
-    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false });
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
 
     // initial connection
     let client_id = common::connect(&mut server_app, &mut client_app);
@@ -318,7 +319,7 @@ fn retained_component_not_removed()
     let initial_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(client_app.world().entities().len(), 1);
 
     client_app.world_mut().entity_mut(initial_client_entity).insert((DummyComponent, Retain::<DummyComponent>::default()));
@@ -336,9 +337,1070 @@ fn retained_component_not_removed()
     let final_client_entity = client_app
         .world_mut()
         .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>, With<DummyComponent>)>()
-        .single(&client_app.world());
+        .single(client_app.world());
     assert_eq!(final_client_entity, initial_client_entity);
     assert_eq!(client_app.world().entities().len(), 1);
 }
 
 //-------------------------------------------------------------------------------------------------------------------
+
+// entity despawn during disconnect is deferred on the client when using deferred despawn mode
+#[test]
+fn disconnect_despawn_deferred()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{
+        cleanup_prespawns: false,
+        despawn_mode: RepairDespawnMode::Deferred(std::time::Duration::ZERO),
+        ..Default::default()
+    });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default())).id();
+    server_app.world_mut().spawn((Replicated, DummyComponent));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let initial_client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>)>()
+        .single(client_app.world());
+    assert_eq!(client_app.world().entities().len(), 2);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // despawn entity
+    server_app.world_mut().despawn(server_entity);
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    // entity is stripped but still alive
+    let entity = client_app.world().entity(initial_client_entity);
+    assert!(entity.contains::<RepairDespawning>());
+    assert!(!entity.contains::<Replicated>());
+    assert!(entity.contains::<BasicComponent>());
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    assert!(!entity_map.to_client().contains_key(&server_entity));
+    assert_eq!(client_app.world().entities().len(), 2);
+
+    // entity is despawned after the timeout
+    client_app.update();
+    assert!(client_app.world().get_entity(initial_client_entity).is_err());
+    assert_eq!(client_app.world().entities().len(), 1);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// deferred despawns are despawned when repair is disabled
+#[test]
+fn deferred_despawn_repair_disabled()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{
+        cleanup_prespawns: false,
+        despawn_mode: RepairDespawnMode::Deferred(std::time::Duration::from_secs(3600)),
+        ..Default::default()
+    });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+    server_app.world_mut().spawn((Replicated, BasicComponent(1)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let initial_client_entity = *entity_map.to_client().get(&server_entity).unwrap();
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // despawn entity
+    server_app.world_mut().despawn(server_entity);
+    server_app.update();
+
+    // reconnect: the entity is waiting to be despawned
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    assert!(client_app.world().entity(initial_client_entity).contains::<RepairDespawning>());
+
+    // disable repair: the entity is despawned without waiting for the timeout
+    client_app.world_mut().resource_mut::<ClientRepairConfig>().enabled = false;
+    common::disconnect(&mut server_app, &mut client_app);
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Dormant);
+    assert!(client_app.world().get_entity(initial_client_entity).is_err());
+    assert_eq!(client_app.world().entities().len(), 1);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// component changed by a client system in the repair tick is still removed if it failed to replicate
+#[test]
+fn locally_changed_component_removed()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .add_systems(PreUpdate,
+            (|mut q: Query<&mut DummyComponent>| q.iter_mut().for_each(|mut c| c.set_changed()))
+                .after(ClientSet::Receive)
+                .before(ClientRepairSet::Prepare)
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent::default(), DummyComponent)).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let initial_client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>, With<DummyComponent>)>()
+        .single(client_app.world());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // remove component
+    server_app.world_mut().entity_mut(server_entity).remove::<DummyComponent>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    let new_client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, (With<Replicated>, With<BasicComponent>, Without<DummyComponent>)>()
+        .single(client_app.world());
+    assert_eq!(new_client_entity, initial_client_entity);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// unchanged components registered with eq repair don't trigger change detection after a reconnect
+#[test]
+fn eq_component_change_detection_preserved()
+{
+    #[derive(Resource, Default)]
+    struct ChangedEntities(Vec<Entity>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair_eq::<BasicComponent>();
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<ChangedEntities>()
+        .add_systems(PreUpdate,
+            (|mut c: ResMut<ChangedEntities>, q: Query<Entity, Changed<BasicComponent>>| c.0 = q.iter().collect())
+                .after(ClientRepairSet::Finish)
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_unchanged = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+    let server_mutated = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(client_app.world().resource::<ChangedEntities>().0.len(), 2);
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_unchanged = *entity_map.to_client().get(&server_unchanged).unwrap();
+    let client_mutated = *entity_map.to_client().get(&server_mutated).unwrap();
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // mutate component
+    *server_app.world_mut().get_mut::<BasicComponent>(server_mutated).unwrap() = BasicComponent(1);
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert_eq!(client_app.world().resource::<ChangedEntities>().0, vec![client_mutated]);
+    assert_eq!(client_app.world().get::<BasicComponent>(client_unchanged), Some(&BasicComponent(0)));
+    assert_eq!(client_app.world().get::<BasicComponent>(client_mutated), Some(&BasicComponent(1)));
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// repair policies are applied to components that fail to replicate after a reconnect
+#[test]
+fn repair_policies()
+{
+    #[derive(Resource, Default)]
+    struct Replaced(Vec<(Entity, BasicComponent, Option<usize>)>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair_policy(RepairPolicy::<BasicComponent>::reset_to_default())
+        .replicate_repair_policy(RepairPolicy::<DummyComponent>::Retain);
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<Replaced>()
+        .add_observer(
+            |mut trigger: Trigger<RepairRemoved<BasicComponent>>, mut r: ResMut<Replaced>, q: Query<&BasicComponent>|
+            {
+                let entity = trigger.entity;
+                let value = std::mem::take(&mut trigger.event_mut().value);
+                r.0.push((entity, value, q.get(entity).ok().map(|c| c.0)));
+            }
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_reset = server_app.world_mut().spawn((Replicated, BasicComponent(5), DummyComponent)).id();
+    let server_retained = server_app.world_mut().spawn((Replicated, BasicComponent(1))).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_reset = *entity_map.to_client().get(&server_reset).unwrap();
+    let client_retained = *entity_map.to_client().get(&server_retained).unwrap();
+    client_app.world_mut().entity_mut(client_retained).insert(DummyComponent);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // remove component
+    server_app.world_mut().entity_mut(server_reset).remove::<BasicComponent>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert_eq!(client_app.world().get::<BasicComponent>(client_reset), Some(&BasicComponent(0)));
+    assert!(client_app.world().entity(client_retained).contains::<DummyComponent>());
+
+    // replaced value is handed to observers
+    assert_eq!(client_app.world().resource::<Replaced>().0, vec![(client_reset, BasicComponent(5), Some(0))]);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// entity-level repair overrides are applied after a reconnect
+#[test]
+fn repair_overrides()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_retain_all = server_app.world_mut().spawn((Replicated, BasicComponent(0), DummyComponent)).id();
+    let server_never_despawn = server_app.world_mut().spawn((Replicated, BasicComponent(1))).id();
+    let server_always_reset = server_app.world_mut().spawn((Replicated, BasicComponent(2))).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_retain_all = *entity_map.to_client().get(&server_retain_all).unwrap();
+    let client_never_despawn = *entity_map.to_client().get(&server_never_despawn).unwrap();
+    let client_always_reset = *entity_map.to_client().get(&server_always_reset).unwrap();
+
+    client_app.world_mut().entity_mut(client_retain_all)
+        .insert(RepairOverride{ retain_all: true, ..Default::default() });
+    client_app.world_mut().entity_mut(client_never_despawn)
+        .insert(RepairOverride{ never_despawn: true, ..Default::default() });
+    client_app.world_mut().entity_mut(client_always_reset)
+        .insert((DummyComponent, Retain::<DummyComponent>::default(), RepairOverride{ always_reset: true, ..Default::default() }));
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    server_app.world_mut().entity_mut(server_retain_all).remove::<DummyComponent>();
+    server_app.world_mut().despawn(server_never_despawn);
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert!(client_app.world().entity(client_retain_all).contains::<DummyComponent>());
+    let never_despawn = client_app.world().entity(client_never_despawn);
+    assert!(!never_despawn.contains::<Replicated>());
+    assert!(never_despawn.contains::<BasicComponent>());
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    assert!(!entity_map.to_server().contains_key(&client_never_despawn));
+    assert!(!client_app.world().entity(client_always_reset).contains::<DummyComponent>());
+    assert_eq!(client_app.world().entities().len(), 3);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// ephemeral entities are despawned as soon as the client disconnects
+#[test]
+fn ephemeral_despawned_on_disconnect()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .add_ephemeral_component::<DummyComponent>();
+
+    // initial connection
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_persistent = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+    let server_marked = server_app.world_mut().spawn((Replicated, BasicComponent(1))).id();
+    server_app.world_mut().spawn((Replicated, DummyComponent));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(client_app.world().entities().len(), 3);
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_persistent = *entity_map.to_client().get(&server_persistent).unwrap();
+    let client_marked = *entity_map.to_client().get(&server_marked).unwrap();
+    client_app.world_mut().entity_mut(client_marked).insert(Ephemeral);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Disconnected);
+
+    assert!(client_app.world().get_entity(client_persistent).is_ok());
+    assert_eq!(client_app.world().entities().len(), 1);
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    assert_eq!(entity_map.to_client().len(), 1);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// component repair is spread across multiple ticks when there is a time budget
+#[test]
+fn component_repair_with_budget()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{
+        cleanup_prespawns: false,
+        repair_budget: Some(std::time::Duration::ZERO),
+        ..Default::default()
+    });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_entities: Vec<Entity> = (0..3)
+        .map(|i| server_app.world_mut().spawn((Replicated, BasicComponent(i), DummyComponent)).id())
+        .collect();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(client_app.world().entities().len(), 3);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    for server_entity in server_entities.iter()
+    {
+        server_app.world_mut().entity_mut(*server_entity).remove::<DummyComponent>();
+    }
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+
+    // one entity is repaired per tick
+    for remaining in (0..3).rev()
+    {
+        client_app.update();
+        let repaired = client_app
+            .world_mut()
+            .query_filtered::<Entity, Without<DummyComponent>>()
+            .iter(client_app.world())
+            .count();
+        assert_eq!(repaired, 3 - remaining);
+
+        let expected = if remaining == 0 { ClientRepairState::Done } else { ClientRepairState::Repairing };
+        assert_eq!(*client_app.world().resource::<ClientRepairState>(), expected);
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// repair rules can be inspected and duplicate registrations are deduplicated
+#[test]
+fn repair_rules_registry()
+{
+    fn noop_repair(_: &mut EntityWorldMut, _: bevy::ecs::component::Tick) {}
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, RepliconPlugins))
+        .replicate_repair::<BasicComponent>()
+        .add_replication_repair_policy(RepairPolicy::<BasicComponent>::Retain)
+        .add_replication_repair_fn(noop_repair)
+        .add_replication_repair_fn(noop_repair);
+
+    let dummy_id = app.world_mut().register_component::<DummyComponent>();
+    let basic_id = app.world_mut().register_component::<BasicComponent>();
+    let rules = app.repair_rules();
+    assert!(rules.contains::<BasicComponent>());
+    assert!(!rules.contains::<DummyComponent>());
+    assert!(rules.contains_id(basic_id));
+    assert!(!rules.contains_id(dummy_id));
+    assert_eq!(rules.untyped_len(), 1);
+
+    let infos: Vec<_> = rules.iter().collect();
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].component_id, basic_id);
+    assert_eq!(infos[0].type_name, std::any::type_name::<BasicComponent>());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// repair systems can read resources and modify entities
+#[test]
+fn repair_system()
+{
+    #[derive(Resource, Default)]
+    struct KeepDummy(Vec<Entity>);
+
+    #[derive(Resource, Default)]
+    struct Visits(usize);
+
+    fn repair_dummy(
+        In((entity, _)) : In<(Entity, bevy::ecs::component::Tick)>,
+        mut commands    : Commands,
+        keep            : Res<KeepDummy>,
+        mut visits      : ResMut<Visits>,
+    ){
+        visits.0 += 1;
+        if keep.0.contains(&entity) { return; }
+        commands.entity(entity).remove::<DummyComponent>();
+    }
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate::<DummyComponent>();
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<KeepDummy>()
+        .init_resource::<Visits>()
+        .add_replication_repair_system(repair_dummy);
+    assert_eq!(client_app.repair_rules().untyped_len(), 1);
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_kept = server_app.world_mut().spawn((Replicated, BasicComponent(0), DummyComponent)).id();
+    let server_removed = server_app.world_mut().spawn((Replicated, BasicComponent(1), DummyComponent)).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_kept = *entity_map.to_client().get(&server_kept).unwrap();
+    let client_removed = *entity_map.to_client().get(&server_removed).unwrap();
+    client_app.world_mut().resource_mut::<KeepDummy>().0.push(client_kept);
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // remove components
+    server_app.world_mut().entity_mut(server_kept).remove::<DummyComponent>();
+    server_app.world_mut().entity_mut(server_removed).remove::<DummyComponent>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert_eq!(client_app.world().resource::<Visits>().0, 2);
+    assert!(client_app.world().entity(client_kept).contains::<DummyComponent>());
+    assert!(!client_app.world().entity(client_removed).contains::<DummyComponent>());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// replication groups register repair for each component and can be retained together
+#[test]
+fn repair_group_components()
+{
+    #[derive(Component, Default, Serialize, Deserialize)]
+    struct Anchor;
+
+    type Group = (BasicComponent, DummyComponent);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<Anchor>()
+        .replicate_repair_group_components::<Group>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+    assert!(client_app.repair_rules().contains::<BasicComponent>());
+    assert!(client_app.repair_rules().contains::<DummyComponent>());
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_repaired = server_app.world_mut().spawn((Replicated, Anchor, BasicComponent(0), DummyComponent)).id();
+    let server_retained = server_app.world_mut().spawn((Replicated, Anchor, BasicComponent(1), DummyComponent)).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_repaired = *entity_map.to_client().get(&server_repaired).unwrap();
+    let client_retained = *entity_map.to_client().get(&server_retained).unwrap();
+    client_app.world_mut().entity_mut(client_retained).insert(Retain::<Group>::default());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // remove the group
+    server_app.world_mut().entity_mut(server_repaired).remove::<Group>();
+    server_app.world_mut().entity_mut(server_retained).remove::<Group>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert!(!client_app.world().entity(client_repaired).contains::<BasicComponent>());
+    assert!(!client_app.world().entity(client_repaired).contains::<DummyComponent>());
+    assert!(client_app.world().entity(client_retained).contains::<BasicComponent>());
+    assert!(client_app.world().entity(client_retained).contains::<DummyComponent>());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// replicated entities are stale until they are re-replicated, and track their last confirmed tick
+#[test]
+fn stale_entities_and_confirmed_ticks()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{
+        cleanup_prespawns: false,
+        despawn_mode: RepairDespawnMode::Deferred(std::time::Duration::from_secs(10)),
+        ..Default::default()
+    });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_kept = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+    let server_despawned = server_app.world_mut().spawn((Replicated, BasicComponent(1))).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let client_kept = *entity_map.to_client().get(&server_kept).unwrap();
+    let client_despawned = *entity_map.to_client().get(&server_despawned).unwrap();
+    let initial_tick = **client_app.world().get::<LastConfirmedTick>(client_kept).unwrap();
+    assert!(!client_app.world().entity(client_kept).contains::<Stale>());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    assert!(client_app.world().entity(client_kept).contains::<Stale>());
+    assert!(client_app.world().entity(client_despawned).contains::<Stale>());
+
+    server_app.world_mut().despawn(server_despawned);
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    assert!(client_app.world().entity(client_kept).contains::<Stale>());
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert!(!client_app.world().entity(client_kept).contains::<Stale>());
+    assert!(client_app.world().entity(client_despawned).contains::<Stale>());
+
+    let confirmed_tick = **client_app.world().get::<LastConfirmedTick>(client_kept).unwrap();
+    let history = client_app.world().get::<bevy_replicon::client::confirm_history::ConfirmHistory>(client_kept).unwrap();
+    assert!(confirmed_tick > initial_tick);
+    assert_eq!(confirmed_tick, history.last_tick());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// user systems can run between repair sets
+#[test]
+fn repair_sets_order()
+{
+    #[derive(Resource, Default)]
+    struct Observed(Vec<(usize, bool)>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<Observed>()
+        .add_systems(PreUpdate,
+            (|mut o: ResMut<Observed>, r: Query<(), With<Replicated>>, b: Query<(), With<BasicComponent>>| {
+                o.0.push((r.iter().count(), b.iter().count() == 1));
+            })
+                .after(ClientRepairSet::DespawnMissing)
+                .before(ClientRepairSet::Components)
+                .run_if(is_repairing)
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_despawned = server_app.world_mut().spawn((Replicated, DummyComponent)).id();
+    let server_mutated = server_app.world_mut().spawn((Replicated, BasicComponent(1), DummyComponent)).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    server_app.world_mut().despawn(server_despawned);
+    server_app.world_mut().entity_mut(server_mutated).remove::<BasicComponent>();
+    server_app.update();
+
+    // reconnect
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    // the missing entity was despawned, but component repair had not run yet
+    assert_eq!(client_app.world().resource::<Observed>().0, vec![(1, true)]);
+    assert_eq!(client_app.world_mut().query::<&BasicComponent>().iter(client_app.world()).count(), 0);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// runtime config changes apply to the next session
+#[test]
+fn runtime_repair_config()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let initial_client_entity = *entity_map.to_client().get(&server_entity).unwrap();
+
+    // disconnect, then change config while disconnected
+    common::disconnect(&mut server_app, &mut client_app);
+    client_app.world_mut().resource_mut::<ClientRepairConfig>().strategy = DisconnectStrategy::Reset;
+    server_app.update();
+
+    // reconnect: the entity is repaired since the config was changed after disconnecting
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    assert!(client_app.world().get_entity(initial_client_entity).is_ok());
+
+    // disconnect: the client state is reset
+    common::disconnect(&mut server_app, &mut client_app);
+    assert!(client_app.world().get_entity(initial_client_entity).is_err());
+    assert_eq!(client_app.world().entities().len(), 0);
+    server_app.update();
+
+    // reconnect: the entity is replicated again
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let reset_client_entity = *entity_map.to_client().get(&server_entity).unwrap();
+    assert_ne!(reset_client_entity, initial_client_entity);
+    assert_eq!(client_app.world().entities().len(), 1);
+
+    // disable repair: replicon's reset runs and the plugin is dormant
+    client_app.world_mut().resource_mut::<ClientRepairConfig>().enabled = false;
+    common::disconnect(&mut server_app, &mut client_app);
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Dormant);
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    assert!(entity_map.to_client().is_empty());
+    assert!(client_app.world().get_entity(reset_client_entity).is_ok());
+    assert!(!client_app.world().entity(reset_client_entity).contains::<Stale>());
+    server_app.update();
+
+    // reconnect: confirmed ticks are still tracked
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let dormant_client_entity = *entity_map.to_client().get(&server_entity).unwrap();
+    let confirmed_tick = **client_app.world().get::<LastConfirmedTick>(dormant_client_entity).unwrap();
+    let history = client_app
+        .world()
+        .get::<bevy_replicon::client::confirm_history::ConfirmHistory>(dormant_client_entity)
+        .unwrap();
+    assert_eq!(confirmed_tick, history.last_tick());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// config changes made while disconnected do not affect the current repair, even after failed connection attempts
+#[test]
+fn repair_config_changed_while_disconnected()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    server_app.world_mut().spawn((Replicated, BasicComponent(0)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, With<Replicated>>()
+        .single(client_app.world());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Disconnected);
+
+    // disable repair, then fail a connection attempt
+    client_app.world_mut().resource_mut::<ClientRepairConfig>().enabled = false;
+    common::fail_connection_attempt(&mut client_app);
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Disconnected);
+
+    // reconnect: the entity is repaired
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    assert!(client_app.world().entity(client_entity).contains::<Replicated>());
+    assert!(!client_app.world().entity(client_entity).contains::<Stale>());
+    assert_eq!(client_app.world().entities().len(), 1);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// forgetting the session despawns session entities and treats the next connection as an initial connection
+#[test]
+fn forget_session()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: true, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let local_entity = client_app.world_mut().spawn(BasicComponent(1)).id();
+    client_app.world_mut().spawn(Prespawned);
+    assert_eq!(client_app.world().entities().len(), 3);
+
+    // disconnect and forget the session
+    common::disconnect(&mut server_app, &mut client_app);
+    client_app.world_mut().commands().forget_session();
+    client_app.world_mut().flush();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Dormant);
+    assert_eq!(client_app.world().entities().len(), 1);
+    assert!(client_app.world().get_entity(local_entity).is_ok());
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    assert!(entity_map.to_client().is_empty());
+    server_app.update();
+
+    // reconnect: this is a fresh session
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Dormant);
+    assert_eq!(client_app.world().entities().len(), 2);
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    assert!(entity_map.to_client().contains_key(&server_entity));
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// components are repaired correctly if the first replication message arrives in the tick where the client connects
+#[test]
+fn first_message_in_connect_tick()
+{
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>();
+    }
+    client_app.add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() });
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(1), DummyComponent)).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, With<Replicated>>()
+        .single(client_app.world());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.world_mut().entity_mut(server_entity).remove::<DummyComponent>();
+    server_app.update();
+
+    // reconnect and receive the first replication message without a client update in between
+    client_app
+        .world_mut()
+        .resource_mut::<RepliconClient>()
+        .set_status(RepliconClientStatus::Connected{ client_id: Some(client_id) });
+    server_app.world_mut().resource_mut::<RepliconServer>().set_running(true);
+    server_app.world_mut().send_event(ServerEvent::ClientConnected{ client_id });
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Disconnected);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert_eq!(client_app.world().get::<BasicComponent>(client_entity), Some(&BasicComponent(1)));
+    assert!(client_app.world().get::<DummyComponent>(client_entity).is_none());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
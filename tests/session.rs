@@ -0,0 +1,473 @@
+//modules
+mod common;
+
+//local shortcuts
+use bevy_replicon_repair::*;
+use common::{BasicComponent, DummyComponent};
+
+//third-party shortcuts
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon::test_app::ServerTestAppExt;
+use serde::{Deserialize, Serialize};
+
+//standard shortcuts
+use std::time::Duration;
+
+
+//-------------------------------------------------------------------------------------------------------------------
+
+#[derive(Component, Default, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct ExtraComponent;
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// reconnecting to the same server process repairs client state
+#[test]
+fn same_server_session_repaired()
+{
+    #[derive(Resource, Default)]
+    struct SessionResets(Vec<SessionReset>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>()
+        .add_repair_session_channel();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<SessionResets>()
+        .add_systems(Update,
+            |mut events: EventReader<SessionReset>, mut resets: ResMut<SessionResets>|
+            {
+                resets.0.extend(events.read().copied());
+            }
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    server_app.world_mut().spawn((Replicated, BasicComponent(0)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, With<Replicated>>()
+        .single(client_app.world());
+
+    // reconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.update();
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert!(client_app.world().get_entity(client_entity).is_ok());
+    assert_eq!(client_app.world().entities().len(), 1);
+    assert!(client_app.world().resource::<SessionResets>().0.is_empty());
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// connecting to a restarted server discards client state from the previous server session
+#[test]
+fn restarted_server_session_reset()
+{
+    #[derive(Resource, Default)]
+    struct SessionResets(Vec<SessionReset>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>()
+        .add_repair_session_channel();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<SessionResets>()
+        .add_systems(Update,
+            |mut events: EventReader<SessionReset>, mut resets: ResMut<SessionResets>|
+            {
+                resets.0.extend(events.read().copied());
+            }
+        );
+    let previous_epoch = server_app.world().resource::<ServerSessionEpoch>().0;
+
+    // initial connection
+    common::connect(&mut server_app, &mut client_app);
+    server_app.world_mut().spawn((Replicated, BasicComponent(0)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let old_client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, With<Replicated>>()
+        .single(client_app.world());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // restart the server
+    let mut server_app = App::new();
+    server_app
+        .add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>()
+        .add_repair_session_channel()
+        .add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    server_app.world_mut().insert_resource(ServerSessionEpoch(previous_epoch.wrapping_add(1)));
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(1))).id();
+
+    // connect to the new server
+    common::connect(&mut server_app, &mut client_app);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert!(client_app.world().get_entity(old_client_entity).is_err());
+    assert_eq!(client_app.world().entities().len(), 1);
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let new_client_entity = *entity_map.to_client().get(&server_entity).unwrap();
+    assert_eq!(client_app.world().get::<BasicComponent>(new_client_entity), Some(&BasicComponent(1)));
+    assert_eq!(client_app.world().resource::<SessionResets>().0, vec![SessionReset{
+        reason: SessionResetReason::ServerRestarted{ previous_epoch, epoch: previous_epoch.wrapping_add(1) }
+    }]);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// clients with different replication rules than the server are reset and stop applying replication
+#[test]
+fn protocol_mismatch_reset()
+{
+    #[derive(Resource, Default)]
+    struct Mismatches(Vec<ProtocolMismatch>);
+
+    #[derive(Resource, Default)]
+    struct ClientMismatches(Vec<ClientProtocolMismatch>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>()
+        .add_repair_session_channel();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<Mismatches>()
+        .add_systems(Update,
+            |mut events: EventReader<ProtocolMismatch>, mut mismatches: ResMut<Mismatches>|
+            {
+                mismatches.0.extend(events.read().copied());
+            }
+        );
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    server_app.world_mut().spawn((Replicated, BasicComponent(0)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(client_app.world().entities().len(), 1);
+    assert!(client_app.world().resource::<Mismatches>().0.is_empty());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+
+    // restart the server with an extra replicated component
+    let mut server_app = App::new();
+    server_app
+        .add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>()
+        .add_repair_session_channel()
+        .add_plugins(bevy_replicon_repair::ServerPlugin::default())
+        .replicate_repair::<ExtraComponent>()
+        .init_resource::<ClientMismatches>()
+        .add_systems(Update,
+            |mut events: EventReader<ClientProtocolMismatch>, mut mismatches: ResMut<ClientMismatches>|
+            {
+                mismatches.0.extend(events.read().copied());
+            }
+        );
+    server_app.world_mut().spawn((Replicated, BasicComponent(1)));
+    server_app.update();
+    let server_fingerprint = replication_fingerprint(server_app.world());
+    let client_fingerprint = replication_fingerprint(client_app.world());
+    assert_ne!(server_fingerprint, client_fingerprint);
+
+    // connect to the new server
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    server_app.update();
+
+    // the client discards the previous session and ignores replication
+    assert_eq!(client_app.world().resource::<Mismatches>().0, vec![ProtocolMismatch{
+        server_fingerprint,
+        client_fingerprint,
+    }]);
+    assert_eq!(client_app.world().entities().len(), 0);
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(client_app.world().entities().len(), 0);
+
+    // blocked messages are discarded instead of accumulating
+    let mut client = client_app.world_mut().resource_mut::<RepliconClient>();
+    assert_eq!(client.receive(bevy_replicon::core::channels::ReplicationChannel::Updates).count(), 0);
+    assert_eq!(client.receive(bevy_replicon::core::channels::ReplicationChannel::Mutations).count(), 0);
+
+    // the server is notified
+    assert_eq!(server_app.world().resource::<ClientMismatches>().0, vec![ClientProtocolMismatch{
+        client_id,
+        server_fingerprint,
+        client_fingerprint,
+    }]);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// repair policies added only on the client don't cause a protocol mismatch
+#[test]
+fn client_only_repair_policy()
+{
+    #[derive(Resource, Default)]
+    struct Mismatches(Vec<ProtocolMismatch>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>()
+        .replicate::<ExtraComponent>()
+        .add_repair_session_channel();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<Mismatches>()
+        .add_systems(Update,
+            |mut events: EventReader<ProtocolMismatch>, mut mismatches: ResMut<Mismatches>|
+            {
+                mismatches.0.extend(events.read().copied());
+            }
+        );
+    client_app.add_replication_repair_policy(RepairPolicy::<ExtraComponent>::Remove);
+    assert_eq!(replication_fingerprint(server_app.world()), replication_fingerprint(client_app.world()));
+
+    // initial connection
+    common::connect(&mut server_app, &mut client_app);
+    server_app.world_mut().spawn((Replicated, BasicComponent(0), ExtraComponent));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert!(client_app.world().resource::<Mismatches>().0.is_empty());
+    assert_eq!(client_app.world_mut().query::<&ExtraComponent>().iter(client_app.world()).count(), 1);
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// reconnecting after a disconnect rejected by the reconnect policy discards client state instead of repairing it
+#[test]
+fn long_disconnect_reset()
+{
+    #[derive(Resource, Default)]
+    struct SessionResets(Vec<SessionReset>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>()
+        .replicate_repair::<DummyComponent>()
+        .add_repair_session_channel();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<SessionResets>()
+        .add_systems(Update,
+            |mut events: EventReader<SessionReset>, mut resets: ResMut<SessionResets>|
+            {
+                resets.0.extend(events.read().copied());
+            }
+        );
+    client_app.world_mut().resource_mut::<ClientRepairConfig>().reconnect_policy =
+        ReconnectPolicy::MaxDuration(Duration::from_secs(3600));
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    let server_entity = server_app.world_mut().spawn((Replicated, BasicComponent(0))).id();
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, With<Replicated>>()
+        .single(client_app.world());
+
+    // short disconnect: repair
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.update();
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+    assert!(client_app.world().get_entity(client_entity).is_ok());
+    assert!(client_app.world().resource::<SessionResets>().0.is_empty());
+
+    // disconnect that is too long: reset
+    client_app.world_mut().resource_mut::<ClientRepairConfig>().reconnect_policy = ReconnectPolicy::Custom(|_| false);
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.update();
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    assert!(client_app.world().get_entity(client_entity).is_err());
+    // the reset does not count as a replication message
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Waiting);
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+    assert_eq!(*client_app.world().resource::<ClientRepairState>(), ClientRepairState::Done);
+
+    assert_eq!(client_app.world().entities().len(), 1);
+    let entity_map = client_app.world().resource::<bevy_replicon::core::server_entity_map::ServerEntityMap>();
+    let new_client_entity = *entity_map.to_client().get(&server_entity).unwrap();
+    assert_ne!(new_client_entity, client_entity);
+    let resets = &client_app.world().resource::<SessionResets>().0;
+    assert_eq!(resets.len(), 1);
+    assert!(matches!(resets[0].reason, SessionResetReason::DisconnectedTooLong{ .. }));
+}
+
+//-------------------------------------------------------------------------------------------------------------------
+
+//-------------------------------------------------------------------------------------------------------------------
+
+// the disconnect duration is measured from the disconnect, not from the most recent failed connection attempt
+#[test]
+fn long_disconnect_reset_after_failed_attempt()
+{
+    #[derive(Resource, Default)]
+    struct SessionResets(Vec<SessionReset>);
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(bevy_replicon::prelude::ServerPlugin {
+                tick_policy: TickPolicy::EveryFrame,
+                ..Default::default()
+            }),
+        ))
+        .replicate_repair::<BasicComponent>();
+    }
+    server_app.add_plugins(bevy_replicon_repair::ServerPlugin::default());
+    client_app
+        .add_plugins(bevy_replicon_repair::ClientPlugin{ cleanup_prespawns: false, ..Default::default() })
+        .init_resource::<SessionResets>()
+        .add_systems(Update,
+            |mut events: EventReader<SessionReset>, mut resets: ResMut<SessionResets>|
+            {
+                resets.0.extend(events.read().copied());
+            }
+        );
+    client_app.world_mut().resource_mut::<ClientRepairConfig>().reconnect_policy =
+        ReconnectPolicy::MaxDuration(Duration::from_millis(200));
+
+    // initial connection
+    let client_id = common::connect(&mut server_app, &mut client_app);
+    server_app.world_mut().spawn((Replicated, BasicComponent(0)));
+
+    server_app.update();
+    server_app.exchange_with_client(&mut client_app);
+    client_app.update();
+
+    let client_entity = client_app
+        .world_mut()
+        .query_filtered::<Entity, With<Replicated>>()
+        .single(client_app.world());
+
+    // disconnect
+    common::disconnect(&mut server_app, &mut client_app);
+    server_app.update();
+    std::thread::sleep(Duration::from_millis(250));
+
+    // failed attempt
+    common::fail_connection_attempt(&mut client_app);
+    std::thread::sleep(Duration::from_millis(150));
+
+    // reconnect: the client was offline for longer than the policy allows
+    common::reconnect(&mut server_app, &mut client_app, client_id);
+    assert!(client_app.world().get_entity(client_entity).is_err());
+    let resets = &client_app.world().resource::<SessionResets>().0;
+    assert_eq!(resets.len(), 1);
+    assert!(matches!(resets[0].reason, SessionResetReason::DisconnectedTooLong{ .. }));
+}